@@ -0,0 +1,28 @@
+CREATE INDEX IF NOT EXISTS completed_task_completed_at_idx
+    ON completed_task ("completed_at" DESC NULLS LAST);
+
+CREATE INDEX IF NOT EXISTS completed_task_date_idx
+    ON completed_task ("date");
+
+CREATE INDEX IF NOT EXISTS completed_task_difficulty_idx
+    ON completed_task ("difficulty");
+
+-- The trigram indexes speed up `history --search`. Creating the pg_trgm
+-- extension needs superuser rights, or CREATE rights on the database since
+-- PostgreSQL 13. Without them the indexes are skipped and the search scans
+-- the table instead. They can be added later by running this block as a
+-- privileged user.
+DO $$
+BEGIN
+    CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+    CREATE INDEX IF NOT EXISTS completed_task_text_trgm_idx
+        ON completed_task USING GIN ("text" gin_trgm_ops);
+
+    CREATE INDEX IF NOT EXISTS completed_task_notes_trgm_idx
+        ON completed_task USING GIN ("notes" gin_trgm_ops);
+EXCEPTION
+    WHEN insufficient_privilege THEN
+        RAISE NOTICE 'pg_trgm unavailable, skipping the trigram indexes of completed_task';
+END
+$$;
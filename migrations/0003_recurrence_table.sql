@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS recurrence (
+    "id" SERIAL PRIMARY KEY,
+    "template" TEXT NOT NULL,
+    "rule" TEXT NOT NULL,
+    "vars" TEXT[] NOT NULL DEFAULT '{}',
+    "start_date" DATE NOT NULL,
+    "last_run" DATE,
+    "last_task_id" UUID
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS pomodoro_session (
+    "id" SERIAL PRIMARY KEY,
+    "task_id" UUID NOT NULL,
+    "task_text" TEXT NOT NULL,
+    "subtask" TEXT,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "ended_at" TIMESTAMPTZ NOT NULL,
+    "planned_secs" INTEGER NOT NULL,
+    "focused_secs" INTEGER NOT NULL,
+    "completed" BOOLEAN NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS pomodoro_session_task_id_idx
+    ON pomodoro_session ("task_id");
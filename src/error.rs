@@ -2,6 +2,7 @@ use std::{env, io, num};
 
 use thiserror::Error;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error(transparent)]
@@ -34,6 +35,9 @@ pub enum AppError {
     #[error("executing command failed: {0}")]
     CmdError(String),
 
+    #[error("invalid task descriptor at column {column}: {msg}")]
+    DescriptorError { column: usize, msg: String },
+
     #[error("running operation for service failed: {0}")]
     ServiceError(String),
 
@@ -42,4 +46,7 @@ pub enum AppError {
 
     #[error(transparent)]
     MigrationError(#[from] sqlx::migrate::MigrateError),
+
+    #[error(transparent)]
+    DBusError(#[from] zbus::Error),
 }
@@ -18,6 +18,8 @@ fn main() -> Result<(), AppError> {
         operation,
         verbose,
         debug,
+        output_format,
+        format_template,
     } = Cli::parse();
     let _ = logger::init(LoggerFlags { verbose, debug });
 
@@ -35,7 +37,7 @@ fn main() -> Result<(), AppError> {
         )
     }
 
-    service::run_operation(operation)?;
+    service::run_operation(operation, output_format, format_template)?;
 
     Ok(())
 }
@@ -1,30 +1,149 @@
 use tokio::runtime::Builder;
 
-use crate::{error::AppError, types::Operation};
+use crate::{
+    error::AppError,
+    types::{Operation, OutputFormat, RecurOperation},
+};
 
+mod config;
+mod daemon;
+pub mod date;
+mod descriptor;
+mod document;
+mod export;
+mod ical;
+mod import;
+mod manage;
+mod output;
+mod priority;
+mod recur;
+mod reorder;
 mod request;
+mod resolve;
+mod status;
+mod template;
 mod tui;
-mod types;
+pub mod types;
 mod util;
+mod width;
 
+use config::load_config;
+use daemon::run_daemon;
+use export::export_tasks;
+use ical::generate_ical;
+use import::import_tasks;
+use manage::TaskChanges;
+use output::init_output;
+use priority::explain_priorities;
+use reorder::priority_reorder_tasks;
+use status::{print_cached_status, show_status};
 use util::*;
 
+/// Whether the operation needs the database. The status is meant to be
+/// polled and the daemon only talks to Habitica, so neither touches it
+fn uses_database(operation: &Option<Operation>) -> bool {
+    !matches!(
+        operation,
+        Some(Operation::Status { .. } | Operation::Daemon { .. })
+    )
+}
+
 async fn run_async(operation: Option<Operation>) -> Result<(), AppError> {
-    run_migrations().await?;
+    if uses_database(&operation) {
+        run_migrations().await?;
+    }
 
     match operation {
         Some(Operation::List { save_json }) => list_tasks(save_json).await?,
-        Some(Operation::Task { descriptor }) => create_task(descriptor).await?,
-        Some(Operation::Reorder) => priority_reorder_tasks().await?,
-        Some(Operation::History) => get_completed_tasks().await?,
+        Some(Operation::Task {
+            descriptor,
+            file,
+            template,
+            vars,
+            dry_run,
+        }) => create_task(descriptor, file, template, vars, dry_run).await?,
+        Some(Operation::Done { query }) => manage::complete(query).await?,
+        Some(Operation::Rm { query, yes }) => manage::remove(query, yes).await?,
+        Some(Operation::Edit {
+            query,
+            name,
+            notes,
+            due,
+            no_due,
+            difficulty,
+            external,
+        }) => {
+            if external {
+                return manage::edit_external(query).await;
+            }
+            let changes = TaskChanges {
+                name,
+                notes,
+                due,
+                no_due,
+                difficulty,
+            };
+            manage::edit(query, changes).await?
+        }
+        Some(Operation::Move { query, to }) => manage::move_to(query, to as usize).await?,
+        Some(Operation::Check {
+            query,
+            subtask,
+            uncheck,
+        }) => manage::check(query, subtask, uncheck).await?,
+        Some(Operation::Status { format, .. }) => show_status(format).await?,
+        Some(Operation::Reorder { dry_run }) => priority_reorder_tasks(dry_run).await?,
+        Some(Operation::Priority { query }) => explain_priorities(query).await?,
+        Some(Operation::History(filter)) => get_completed_tasks(filter).await?,
+        Some(Operation::Export {
+            format,
+            tasks,
+            output,
+        }) => export_tasks(format, tasks, output).await?,
+        Some(Operation::Ical { output, watch }) => generate_ical(output, watch).await?,
+        Some(Operation::Import {
+            file,
+            format,
+            dry_run,
+            yes,
+        }) => import_tasks(file, format, dry_run, yes).await?,
+        Some(Operation::Daemon { once }) => run_daemon(once).await?,
+        Some(Operation::Recur(recur_operation)) => match recur_operation {
+            RecurOperation::Add {
+                template,
+                rule,
+                vars,
+                start,
+            } => recur::add_recurrence(template, rule, vars, start).await?,
+            RecurOperation::List => recur::list_recurrences().await?,
+            RecurOperation::Rm { id } => recur::remove_recurrence(id).await?,
+            RecurOperation::Run { dry_run } => recur::run_recurrences(dry_run).await?,
+        },
         None => tui::run().await?,
     };
 
     Ok(())
 }
 
-pub fn run_operation(operation: Option<Operation>) -> Result<(), AppError> {
-    assert_service_installed()?;
+pub fn run_operation(
+    operation: Option<Operation>,
+    output: OutputFormat,
+    template: Option<String>,
+) -> Result<(), AppError> {
+    assert_service_installed(uses_database(&operation))?;
+    load_config()?;
+    init_output(output, template)?;
+
+    // A fresh cached status is printed without starting the runtime
+    if let Some(Operation::Status {
+        format,
+        refresh: false,
+    }) = &operation
+    {
+        if print_cached_status(format.as_deref())? {
+            return Ok(());
+        }
+    }
 
     // Create async runtime to enable fetching Habitica API data
     let runtime = Builder::new_multi_thread()
@@ -0,0 +1,57 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use super::{
+    daemon::DaemonConfig, priority::PriorityConfig, status::StatusConfig, template::TemplateConfig,
+    tui::pomodoro::PomodoroConfig,
+};
+use crate::{error::AppError, util::build_config_path};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// User configuration read from ~/.config/habitui/config.json. Every section
+/// is optional and falls back to the default behaviour of habitui
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub priority: PriorityConfig,
+    pub status: StatusConfig,
+    pub templates: TemplateConfig,
+    pub daemon: DaemonConfig,
+    pub pomodoro: PomodoroConfig,
+}
+
+pub fn get_config_path() -> Result<String, AppError> {
+    let dir = build_config_path()?;
+    Ok(format!("{dir}/config.json"))
+}
+
+/// Read and validate the config file. A missing file is not an error
+pub fn load_config() -> Result<(), AppError> {
+    let path = get_config_path()?;
+    let config = match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str::<Config>(&data)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Config::default(),
+        Err(e) => Err(e)?,
+    };
+
+    config
+        .priority
+        .validate()
+        .and_then(|_| config.status.validate())
+        .and_then(|_| config.templates.validate())
+        .and_then(|_| config.daemon.validate(&config.priority))
+        .and_then(|_| config.pomodoro.validate())
+        .map_err(|e| AppError::ServiceError(format!("invalid config {path}: {e}")))?;
+
+    let _ = CONFIG.set(config);
+    Ok(())
+}
+
+/// Get the loaded config, or the default config if none was loaded
+pub fn get_config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+
+use log::{error, info};
+use serde::{Deserialize, Deserializer};
+use sqlx::types::uuid::Uuid;
+use time::{macros::format_description, Duration, OffsetDateTime, Time};
+use tokio::time::sleep;
+use zbus::{proxy, zvariant::Value, Connection};
+
+use super::{
+    config::get_config,
+    date::{countdown, to_local},
+    output::print_status,
+    priority::PriorityConfig,
+    request::{fetch_tags, fetch_tasks},
+    types::Task,
+    util::{register_tags, ArrayRes},
+};
+use crate::{error::AppError, util::build_config_path};
+
+const APP_NAME: &str = "habitui";
+
+// Urgency levels of the notification specification
+const URGENCY_NORMAL: u8 = 1;
+const URGENCY_CRITICAL: u8 = 2;
+
+/// How long before a task is due to notify about it, e.g. 30m, 3h, 1d or 2w
+#[derive(Clone, Copy)]
+pub struct LeadTime(Duration);
+
+impl LeadTime {
+    fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let split = input
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(input.len());
+        let (n, unit) = input.split_at(split);
+        let n: i64 = n.parse().ok()?;
+        let duration = match unit.trim() {
+            "m" | "min" | "minutes" => Duration::minutes(n),
+            "h" | "hours" => Duration::hours(n),
+            "d" | "days" => Duration::days(n),
+            "w" | "weeks" => Duration::weeks(n),
+            _ => return None,
+        };
+        Some(Self(duration))
+    }
+}
+
+impl<'de> Deserialize<'de> for LeadTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let input = String::deserialize(deserializer)?;
+        LeadTime::parse(&input).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "invalid lead time '{input}', expected e.g. 30m, 3h, 1d or 2w"
+            ))
+        })
+    }
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<Time, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let input = String::deserialize(deserializer)?;
+    Time::parse(&input, format_description!("[hour]:[minute]")).map_err(|_| {
+        serde::de::Error::custom(format!("invalid time '{input}', expected e.g. 22:00"))
+    })
+}
+
+/// Time of day during which no notifications are sent. The quiet hours may
+/// span midnight, e.g. from 22:00 to 07:00
+#[derive(Deserialize, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHours {
+    #[serde(deserialize_with = "deserialize_time")]
+    pub start: Time,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub end: Time,
+}
+
+impl QuietHours {
+    fn contains(&self, time: Time) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Notifications sent by `habitui daemon`
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Minutes between checks of the due dates and reminders
+    pub interval: u64,
+    /// How long before the end of its due date a task is notified about
+    pub lead_time: LeadTime,
+    /// Lead times of the priority levels which differ from lead_time
+    pub lead_times: BTreeMap<String, LeadTime>,
+    /// Notifications held back during the quiet hours are sent once they end
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            interval: 5,
+            lead_time: LeadTime(Duration::DAY),
+            lead_times: BTreeMap::new(),
+            quiet_hours: None,
+        }
+    }
+}
+
+impl DaemonConfig {
+    pub fn validate(&self, priority: &PriorityConfig) -> Result<(), String> {
+        for level in self.lead_times.keys() {
+            if !priority
+                .levels
+                .iter()
+                .any(|l| l.eq_ignore_ascii_case(level))
+            {
+                return Err(format!("unknown priority level '{level}' in lead_times"));
+            }
+        }
+        Ok(())
+    }
+
+    fn lead_time_of(&self, task: &Task) -> Duration {
+        let level = get_config().priority.level_name(task.get_priority());
+        self.lead_times
+            .iter()
+            .find(|(l, _)| l.eq_ignore_ascii_case(level))
+            .map_or(self.lead_time, |(_, lead_time)| *lead_time)
+            .0
+    }
+
+    fn check_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.interval.max(1) * 60)
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, &Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Reminder set for a TODO in Habitica
+#[derive(Deserialize)]
+struct Reminder {
+    id: String,
+    #[serde(deserialize_with = "time::serde::iso8601::deserialize")]
+    time: OffsetDateTime,
+}
+
+#[derive(Deserialize)]
+struct TaskReminders {
+    #[serde(rename = "_id")]
+    id: Uuid,
+    #[serde(default)]
+    reminders: Vec<Reminder>,
+}
+
+struct Notification {
+    /// Identifies what the notification is about, so that it is sent once
+    key: String,
+    summary: String,
+    body: String,
+    urgency: u8,
+}
+
+fn get_sent_path() -> Result<String, AppError> {
+    let dir = build_config_path()?;
+    Ok(format!("{dir}/daemon_sent.json"))
+}
+
+/// Keys of the notifications sent so far, kept across restarts of the daemon
+fn load_sent() -> HashSet<String> {
+    get_sent_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_sent(sent: &HashSet<String>) -> Result<(), AppError> {
+    fs::write(get_sent_path()?, serde_json::to_string(sent)?)?;
+    Ok(())
+}
+
+/// Notifications of the tasks which are due within their lead time and of
+/// the reminders whose time has come since the last check. A task is due at
+/// the end of its due date
+fn collect_notifications(
+    tasks: &[Task],
+    reminders: &[TaskReminders],
+    since: OffsetDateTime,
+    now: OffsetDateTime,
+) -> Vec<Notification> {
+    let config = &get_config().daemon;
+    let highest = get_config().priority.highest();
+    let mut notifications = Vec::new();
+
+    for task in tasks {
+        let body = task.notes.clone().unwrap_or_default();
+        if let Some(date) = task.date {
+            // Due dates are stored at UTC midnight, so their calendar date
+            // is the day they are due in the local timezone
+            let day = date.date();
+            let end_of_day = day
+                .next_day()
+                .map(|d| d.midnight().assume_offset(now.offset()));
+            if end_of_day.is_some_and(|end| now >= end - config.lead_time_of(task)) {
+                let is_overdue = end_of_day.is_some_and(|end| now >= end);
+                let summary = if is_overdue {
+                    format!("Overdue ({}): {}", countdown(date), task.text)
+                } else {
+                    format!("Due {}: {}", countdown(date), task.text)
+                };
+                let urgency = if is_overdue || task.get_priority() == highest {
+                    URGENCY_CRITICAL
+                } else {
+                    URGENCY_NORMAL
+                };
+                notifications.push(Notification {
+                    key: format!("{}/due/{day}", task.id),
+                    summary,
+                    body: body.clone(),
+                    urgency,
+                });
+            }
+        }
+
+        let task_reminders = reminders.iter().filter(|r| r.id == task.id);
+        for reminder in task_reminders.flat_map(|r| &r.reminders) {
+            if since < reminder.time && reminder.time <= now {
+                notifications.push(Notification {
+                    key: format!("{}/reminder/{}/{}", task.id, reminder.id, reminder.time),
+                    summary: format!("Reminder: {}", task.text),
+                    body: body.clone(),
+                    urgency: URGENCY_NORMAL,
+                });
+            }
+        }
+    }
+
+    notifications
+}
+
+/// Send the notifications which have not been sent yet and move `since` to
+/// the time of the check once all were sent. Returns the number of
+/// notifications sent
+async fn check(
+    proxy: &NotificationsProxy<'_>,
+    sent: &mut HashSet<String>,
+    since: &mut OffsetDateTime,
+) -> Result<usize, AppError> {
+    let now = to_local(OffsetDateTime::now_utc());
+    if let Some(quiet_hours) = get_config().daemon.quiet_hours {
+        if quiet_hours.contains(now.time()) {
+            info!("Quiet hours, holding back notifications");
+            return Ok(0);
+        }
+    }
+
+    // Priority rules matching on tags need to know the tag names
+    if get_config().priority.uses_tags() {
+        register_tags(fetch_tags().await?);
+    }
+    let raw_tasks = fetch_tasks("todos").await?;
+    let tasks = serde_json::from_str::<ArrayRes<Task>>(&raw_tasks)?.data;
+    let reminders = serde_json::from_str::<ArrayRes<TaskReminders>>(&raw_tasks)?.data;
+
+    // Forget the notifications of tasks which no longer exist
+    sent.retain(|key| tasks.iter().any(|t| key.starts_with(&t.id.to_string())));
+
+    let mut n_sent = 0;
+    for notification in collect_notifications(&tasks, &reminders, *since, now) {
+        if sent.contains(&notification.key) {
+            continue;
+        }
+        let urgency = Value::U8(notification.urgency);
+        let hints = HashMap::from([("urgency", &urgency)]);
+        let result = proxy
+            .notify(
+                APP_NAME,
+                0,
+                "",
+                &notification.summary,
+                &notification.body,
+                &[],
+                hints,
+                -1,
+            )
+            .await;
+        if let Err(e) = result {
+            // Whatever was sent before the failure is still remembered
+            save_sent(sent)?;
+            Err(e)?;
+        }
+        info!("Notified: {}", notification.summary);
+        sent.insert(notification.key);
+        n_sent += 1;
+    }
+    save_sent(sent)?;
+    *since = now;
+
+    Ok(n_sent)
+}
+
+/// Notify about approaching due dates and Habitica reminders over the
+/// desktop notification service of the D-Bus session bus, which is found
+/// through DBUS_SESSION_BUS_ADDRESS
+pub async fn run_daemon(once: bool) -> Result<(), AppError> {
+    let connection = Connection::session().await?;
+    let proxy = NotificationsProxy::new(&connection).await?;
+    let mut sent = load_sent();
+    // Reminders which passed before the daemon started are not sent, apart
+    // from those of the last interval
+    let interval = get_config().daemon.check_interval();
+    let mut since = to_local(OffsetDateTime::now_utc()) - interval;
+
+    loop {
+        match check(&proxy, &mut sent, &mut since).await {
+            Ok(n_sent) if once => {
+                print_status(&format!("Sent {n_sent} notifications"));
+                return Ok(());
+            }
+            Ok(n_sent) => info!("Sent {n_sent} notifications"),
+            Err(e) if once => return Err(e),
+            // Keep running if Habitica or the notification service is
+            // unavailable for a moment
+            Err(e) => error!("Sending notifications failed: {e}"),
+        }
+
+        sleep(interval).await;
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{Datelike, Local};
+use time::{Date, Duration, Month, OffsetDateTime, UtcOffset, Weekday};
+
+/// Current date in the local timezone of the user
+pub fn local_today() -> Date {
+    let now = Local::now().date_naive();
+    Date::from_ordinal_date(now.year(), now.ordinal() as u16)
+        .unwrap_or_else(|_| OffsetDateTime::now_utc().date())
+}
+
+/// Date and time in the local timezone of the user
+pub fn to_local(datetime: OffsetDateTime) -> OffsetDateTime {
+    let offset = Local::now().offset().local_minus_utc();
+    UtcOffset::from_whole_seconds(offset).map_or(datetime, |o| datetime.to_offset(o))
+}
+
+/// Days until a due date relative to today, e.g. "in 3 days". Due dates are
+/// stored at UTC midnight, so only their calendar date is compared
+pub fn countdown(date: OffsetDateTime) -> String {
+    let days = (date.date() - local_today()).whole_days();
+    match days {
+        0 => "today".into(),
+        1 => "tomorrow".into(),
+        -1 => "yesterday".into(),
+        d if d > 1 => format!("in {d} days"),
+        d => format!("{} days ago", -d),
+    }
+}
+
+pub fn parse_weekday(input: &str) -> Option<Weekday> {
+    let weekday = match input {
+        "mon" | "monday" => Weekday::Monday,
+        "tue" | "tues" | "tuesday" => Weekday::Tuesday,
+        "wed" | "wednesday" => Weekday::Wednesday,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thursday,
+        "fri" | "friday" => Weekday::Friday,
+        "sat" | "saturday" => Weekday::Saturday,
+        "sun" | "sunday" => Weekday::Sunday,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+/// Add months to a date, moving the day to the end of the month if the month
+/// is too short, e.g. Jan 31 + 1 month is Feb 28
+fn add_months(date: Date, n: i64) -> Option<Date> {
+    let months = i64::from(date.year()) * 12 + i64::from(date.month() as u8 - 1) + n;
+    let year = i32::try_from(months.div_euclid(12)).ok()?;
+    let month = Month::try_from(months.rem_euclid(12) as u8 + 1).ok()?;
+    let day = date.day().min(month.length(year));
+
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+fn add_offset(date: Date, n: i64, unit: &str) -> Option<Date> {
+    match unit {
+        "" | "d" | "day" | "days" => date.checked_add(Duration::days(n)),
+        "w" | "week" | "weeks" => date.checked_add(Duration::weeks(n)),
+        "m" | "month" | "months" => add_months(date, n),
+        "y" | "year" | "years" => add_months(date, n.checked_mul(12)?),
+        _ => None,
+    }
+}
+
+/// Parse an offset such as `+3d`, `-1w` or `+2m`
+fn parse_offset(date: Date, input: &str) -> Option<Date> {
+    let (sign, rest) = match input.split_at_checked(1)? {
+        ("+", rest) => (1, rest),
+        ("-", rest) => (-1, rest),
+        _ => return None,
+    };
+    let split = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (n, unit) = rest.split_at(split);
+    let n: i64 = n.parse().ok()?;
+
+    add_offset(date, sign * n, unit)
+}
+
+fn start_of_next_week(date: Date) -> Date {
+    date.next_occurrence(Weekday::Monday)
+}
+
+fn end_of(date: Date, unit: &str) -> Option<Date> {
+    match unit {
+        "week" => Some(start_of_next_week(date) - Duration::DAY),
+        "month" => date.replace_day(date.month().length(date.year())).ok(),
+        "year" => Date::from_calendar_date(date.year(), Month::December, 31).ok(),
+        _ => None,
+    }
+}
+
+/// Resolve a natural language date relative to `today`. Understood forms are
+///
+/// - `today`, `tomorrow` and `yesterday`
+/// - a weekday such as `fri`, meaning its next occurrence after today
+/// - `next week`, `next month` and `next year`, meaning the first day of each,
+///   and `next fri`, meaning the Friday of next week
+/// - `end of week`, `end of month` and `end of year`, or `eow`, `eom`, `eoy`
+/// - offsets such as `+3d`, `-1w`, `+2m`, `+1y` and `in 3 days`
+pub fn parse_natural_date(input: &str, today: Date) -> Option<Date> {
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["today"] | ["tod"] => Some(today),
+        ["tomorrow"] | ["tom"] | ["tmr"] => today.next_day(),
+        ["yesterday"] => today.previous_day(),
+        ["next", "week"] => Some(start_of_next_week(today)),
+        ["next", "month"] => add_months(today.replace_day(1).ok()?, 1),
+        ["next", "year"] => Date::from_calendar_date(today.year() + 1, Month::January, 1).ok(),
+        ["next", day] => {
+            let weekday = parse_weekday(day)?;
+            let monday = start_of_next_week(today);
+            monday.checked_add(Duration::days(weekday.number_days_from_monday().into()))
+        }
+        ["end", "of", unit] => end_of(today, unit),
+        ["eow"] => end_of(today, "week"),
+        ["eom"] => end_of(today, "month"),
+        ["eoy"] => end_of(today, "year"),
+        ["in", n, unit] => add_offset(today, n.parse().ok()?, unit),
+        [word] => match parse_weekday(word) {
+            Some(weekday) => Some(today.next_occurrence(weekday)),
+            None => parse_offset(today, word),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    // A Wednesday
+    const TODAY: Date = date!(2024 - 01 - 31);
+
+    fn parse(input: &str) -> Option<Date> {
+        parse_natural_date(input, TODAY)
+    }
+
+    #[test]
+    fn parses_relative_days() {
+        assert_eq!(parse("today"), Some(TODAY));
+        assert_eq!(parse(" Tomorrow "), Some(date!(2024 - 02 - 01)));
+        assert_eq!(parse("yesterday"), Some(date!(2024 - 01 - 30)));
+    }
+
+    #[test]
+    fn parses_weekdays_after_today() {
+        assert_eq!(parse("fri"), Some(date!(2024 - 02 - 02)));
+        assert_eq!(parse("wednesday"), Some(date!(2024 - 02 - 07)));
+        assert_eq!(parse("next fri"), Some(date!(2024 - 02 - 09)));
+    }
+
+    #[test]
+    fn parses_start_and_end_of_periods() {
+        assert_eq!(parse("next week"), Some(date!(2024 - 02 - 05)));
+        assert_eq!(parse("next month"), Some(date!(2024 - 02 - 01)));
+        assert_eq!(parse("next year"), Some(date!(2025 - 01 - 01)));
+        assert_eq!(parse("eow"), Some(date!(2024 - 02 - 04)));
+        assert_eq!(parse("end of month"), Some(date!(2024 - 01 - 31)));
+        assert_eq!(parse("eoy"), Some(date!(2024 - 12 - 31)));
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse("+3d"), Some(date!(2024 - 02 - 03)));
+        assert_eq!(parse("+3"), Some(date!(2024 - 02 - 03)));
+        assert_eq!(parse("-1w"), Some(date!(2024 - 01 - 24)));
+        assert_eq!(parse("in 2 weeks"), Some(date!(2024 - 02 - 14)));
+        assert_eq!(parse("+1y"), Some(date!(2025 - 01 - 31)));
+    }
+
+    #[test]
+    fn clamps_month_offsets_to_end_of_month() {
+        assert_eq!(parse("+1m"), Some(date!(2024 - 02 - 29)));
+        assert_eq!(parse("+13m"), Some(date!(2025 - 02 - 28)));
+    }
+
+    #[test]
+    fn rejects_unknown_input() {
+        for input in ["", "someday", "+d", "3d", "in three days", "next decade"] {
+            assert_eq!(parse(input), None, "'{input}' should be rejected");
+        }
+    }
+}
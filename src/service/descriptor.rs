@@ -0,0 +1,481 @@
+use std::fmt;
+
+use super::{
+    types::{SubTask, Task},
+    util::{parse_difficulty, parse_due_date},
+};
+use crate::error::AppError;
+
+/// Task parsed from a descriptor along with the names of its tags, which are
+/// resolved to Habitica tag IDs only when the task is created
+pub struct TaskDescriptor {
+    pub task: Task,
+    pub tags: Vec<String>,
+}
+
+impl fmt::Display for TaskDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.task)?;
+        if !self.tags.is_empty() {
+            writeln!(f, "Tags: {}", self.tags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Key {
+    Name,
+    Difficulty,
+    Notes,
+    Due,
+    Checklist,
+    Tag,
+}
+
+// Order in which fields without a key are assigned
+const POSITIONAL_KEYS: [Key; 5] = [
+    Key::Name,
+    Key::Difficulty,
+    Key::Notes,
+    Key::Due,
+    Key::Checklist,
+];
+
+impl Key {
+    fn parse(key: &str) -> Option<Self> {
+        match key.to_lowercase().as_str() {
+            "name" => Some(Key::Name),
+            "diff" | "difficulty" => Some(Key::Difficulty),
+            "notes" => Some(Key::Notes),
+            "due" => Some(Key::Due),
+            "check" | "checklist" => Some(Key::Checklist),
+            "tag" | "tags" => Some(Key::Tag),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Key::Name => "name",
+            Key::Difficulty => "diff",
+            Key::Notes => "notes",
+            Key::Due => "due",
+            Key::Checklist => "check",
+            Key::Tag => "tag",
+        }
+    }
+}
+
+/// Character of a descriptor and whether it was quoted or escaped, which
+/// makes it part of a value even if it would otherwise be syntax
+#[derive(Clone, Copy)]
+struct Char {
+    c: char,
+    literal: bool,
+}
+
+/// Part of a field value between `;` separators
+struct Segment {
+    chars: Vec<Char>,
+    column: usize,
+}
+
+/// Text of the given characters without surrounding whitespace, unless the
+/// whitespace was quoted
+fn trimmed_text(chars: &[Char]) -> String {
+    let is_space = |c: &Char| !c.literal && c.c.is_whitespace();
+    let start = chars.iter().position(|c| !is_space(c));
+    let end = chars.iter().rposition(|c| !is_space(c));
+    match (start, end) {
+        (Some(start), Some(end)) => chars[start..=end].iter().map(|c| c.c).collect(),
+        _ => String::new(),
+    }
+}
+
+impl Segment {
+    fn text(&self) -> String {
+        trimmed_text(&self.chars)
+    }
+}
+
+struct Field {
+    key: Option<Key>,
+    column: usize,
+    segments: Vec<Segment>,
+}
+
+impl Field {
+    fn new(column: usize) -> Self {
+        Self {
+            key: None,
+            column,
+            segments: vec![Segment {
+                chars: Vec::new(),
+                column,
+            }],
+        }
+    }
+
+    /// Whole value of the field as it was written. Separators only have a
+    /// meaning in checklists and tags, elsewhere they are a part of the value
+    fn text(&self) -> String {
+        let separator = Char {
+            c: ';',
+            literal: true,
+        };
+        let chars: Vec<Char> = self
+            .segments
+            .iter()
+            .enumerate()
+            .flat_map(|(i, s)| {
+                (i > 0)
+                    .then_some(separator)
+                    .into_iter()
+                    .chain(s.chars.clone())
+            })
+            .collect();
+        trimmed_text(&chars)
+    }
+
+    /// Column where the value starts, skipping whitespace after the key
+    fn value_column(&self) -> usize {
+        let first = &self.segments[0];
+        let n_spaces = first
+            .chars
+            .iter()
+            .take_while(|c| !c.literal && c.c.is_whitespace())
+            .count();
+        first.column + n_spaces
+    }
+
+    fn current_segment(&mut self) -> &mut Segment {
+        self.segments.last_mut().unwrap()
+    }
+
+    /// If everything read so far in the field is a known key, consume it
+    fn take_key(&mut self) -> bool {
+        if self.key.is_some() || self.segments.len() > 1 {
+            return false;
+        }
+        let segment = &self.segments[0];
+        if segment.chars.iter().any(|c| c.literal) {
+            return false;
+        }
+        let Some(key) = Key::parse(segment.text().as_str()) else {
+            return false;
+        };
+
+        self.key = Some(key);
+        let column = segment.column + segment.chars.len() + 1;
+        self.segments = vec![Segment {
+            chars: Vec::new(),
+            column,
+        }];
+        true
+    }
+}
+
+fn descriptor_error(column: usize, msg: String) -> AppError {
+    AppError::DescriptorError { column, msg }
+}
+
+/// Split a descriptor into fields. Columns are 1-based character positions
+fn tokenize(descriptor: &str) -> Result<Vec<Field>, AppError> {
+    let mut fields = vec![Field::new(1)];
+    let mut quote_column: Option<usize> = None;
+    let mut chars = descriptor.chars().enumerate();
+
+    while let Some((i, c)) = chars.next() {
+        let column = i + 1;
+        let field = fields.last_mut().unwrap();
+
+        match c {
+            '\\' => {
+                let Some((_, escaped)) = chars.next() else {
+                    Err(descriptor_error(column, "nothing to escape".into()))?
+                };
+                let c = match escaped {
+                    'n' => '\n',
+                    e => e,
+                };
+                field
+                    .current_segment()
+                    .chars
+                    .push(Char { c, literal: true });
+            }
+            '"' => {
+                quote_column = match quote_column {
+                    Some(_) => None,
+                    None => Some(column),
+                };
+            }
+            _ if quote_column.is_some() => {
+                field
+                    .current_segment()
+                    .chars
+                    .push(Char { c, literal: true });
+            }
+            ',' => fields.push(Field::new(column + 1)),
+            ';' => field.segments.push(Segment {
+                chars: Vec::new(),
+                column: column + 1,
+            }),
+            ':' if field.take_key() => {}
+            _ => field
+                .current_segment()
+                .chars
+                .push(Char { c, literal: false }),
+        }
+    }
+
+    if let Some(column) = quote_column {
+        Err(descriptor_error(column, "unterminated quote".into()))?;
+    }
+
+    Ok(fields)
+}
+
+/// Parse a task descriptor. Fields are separated by commas and given either
+/// in the order <name>,<difficulty>,<notes>,<due>,<checklist> or by name as
+/// `name:`, `diff:`, `notes:`, `due:`, `check:` and `tag:`. Named fields may
+/// come in any order, and a single field without a name among them is the
+/// name of the task. Checklist items and tags are separated by semicolons.
+/// Quote a value or escape single characters with a backslash to use
+/// separators in it
+pub fn parse_task_descriptor(descriptor: &str) -> Result<TaskDescriptor, AppError> {
+    let mut task = Task::default();
+    let mut tags: Vec<String> = Vec::new();
+    let mut given: Vec<Key> = Vec::new();
+    let mut positional = POSITIONAL_KEYS.iter();
+    let fields = tokenize(descriptor)?;
+    let n_unnamed = fields
+        .iter()
+        .filter(|f| f.key.is_none() && !f.text().is_empty())
+        .count();
+    // A name which starts like a named field, e.g. "due: taxes", is read as
+    // that field. The fields after it are then only unambiguous if a single
+    // one of them has no name, which is the name of the task
+    let leading_key = fields
+        .first()
+        .and_then(|f| f.key)
+        .filter(|k| *k != Key::Name);
+    let ambiguous_error = |key: Key| {
+        let name = key.name();
+        descriptor_error(
+            1,
+            format!("the first field is read as field '{name}', quote the name if it starts with '{name}:' or name the other fields"),
+        )
+    };
+
+    for field in fields {
+        let key = match (field.key, leading_key) {
+            (Some(key), _) => key,
+            // Empty positional fields only hold the place of a field
+            (None, _) if field.text().is_empty() => {
+                positional.find(|k| !given.contains(k));
+                continue;
+            }
+            (None, Some(_)) if n_unnamed == 1 && !given.contains(&Key::Name) => Key::Name,
+            (None, Some(leading)) if !given.contains(&Key::Name) => Err(ambiguous_error(leading))?,
+            (None, _) => *positional
+                .find(|k| !given.contains(k))
+                .ok_or(descriptor_error(
+                    field.column,
+                    "too many fields, name the field or quote the commas in it".into(),
+                ))?,
+        };
+
+        if key != Key::Tag && given.contains(&key) {
+            Err(descriptor_error(
+                field.column,
+                format!("field '{}' given more than once", key.name()),
+            ))?;
+        }
+        given.push(key);
+
+        let column = field.value_column();
+        let text = field.text();
+        match key {
+            Key::Name => task.text = text,
+            Key::Notes => task.notes = Some(text).filter(|n| !n.is_empty()),
+            Key::Difficulty => {
+                task.difficulty = parse_difficulty(&text).map_err(|_| {
+                    descriptor_error(
+                        column,
+                        format!(
+                            "unknown difficulty '{text}', expected trivial, easy, medium or hard"
+                        ),
+                    )
+                })?;
+            }
+            Key::Due if text.is_empty() => {}
+            Key::Due => {
+                task.date =
+                    Some(parse_due_date(&text).map_err(|_| {
+                        descriptor_error(column, format!("invalid due date '{text}'"))
+                    })?);
+            }
+            Key::Checklist => {
+                let checklist = task.checklist.get_or_insert_with(Vec::new);
+                checklist.extend(
+                    field
+                        .segments
+                        .iter()
+                        .map(|s| s.text())
+                        .filter(|t| !t.is_empty())
+                        .map(|text| SubTask {
+                            text,
+                            completed: false,
+                        }),
+                );
+            }
+            Key::Tag => tags.extend(
+                field
+                    .segments
+                    .iter()
+                    .map(|s| s.text())
+                    .filter(|t| !t.is_empty()),
+            ),
+        }
+    }
+
+    if task.text.is_empty() {
+        if let Some(key) = leading_key {
+            Err(ambiguous_error(key))?;
+        }
+        Err(descriptor_error(1, "task name is required".into()))?;
+    }
+
+    Ok(TaskDescriptor { task, tags })
+}
+
+/// Point out the column of a descriptor error below the descriptor
+pub fn print_descriptor_error(descriptor: &str, e: &AppError) {
+    if let AppError::DescriptorError { column, msg } = e {
+        eprintln!("{descriptor}");
+        eprintln!("{:>column$} {msg}", "^");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+    use crate::service::types::Difficulty;
+
+    fn parse(descriptor: &str) -> TaskDescriptor {
+        parse_task_descriptor(descriptor).unwrap()
+    }
+
+    fn error_column(descriptor: &str) -> usize {
+        match parse_task_descriptor(descriptor) {
+            Err(AppError::DescriptorError { column, .. }) => column,
+            _ => panic!("expected a descriptor error for '{descriptor}'"),
+        }
+    }
+
+    fn checklist(descriptor: &TaskDescriptor) -> Vec<&str> {
+        descriptor
+            .task
+            .checklist
+            .iter()
+            .flatten()
+            .map(|s| s.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parses_positional_fields() {
+        let parsed = parse("Buy milk,hard,From the store,2024-01-01,Milk;Eggs");
+
+        assert_eq!(parsed.task.text, "Buy milk");
+        assert_eq!(parsed.task.difficulty, Difficulty::HARD);
+        assert_eq!(parsed.task.notes.as_deref(), Some("From the store"));
+        assert_eq!(
+            parsed.task.date.map(|d| d.date()),
+            Some(date!(2024 - 01 - 01))
+        );
+        assert_eq!(checklist(&parsed), ["Milk", "Eggs"]);
+    }
+
+    #[test]
+    fn empty_fields_hold_their_place() {
+        let parsed = parse("Buy milk,,From the store");
+
+        assert_eq!(parsed.task.difficulty, Difficulty::EASY);
+        assert_eq!(parsed.task.notes.as_deref(), Some("From the store"));
+    }
+
+    #[test]
+    fn parses_named_fields_in_any_order() {
+        let parsed = parse("Buy milk,tag:errands;home,due:2024-01-01,diff:medium");
+
+        assert_eq!(parsed.task.text, "Buy milk");
+        assert_eq!(parsed.task.difficulty, Difficulty::MEDIUM);
+        assert_eq!(
+            parsed.task.date.map(|d| d.date()),
+            Some(date!(2024 - 01 - 01))
+        );
+        assert_eq!(parsed.tags, ["errands", "home"]);
+    }
+
+    #[test]
+    fn reads_single_unnamed_field_after_named_ones_as_name() {
+        let parsed = parse("tag:errands,Buy milk");
+
+        assert_eq!(parsed.task.text, "Buy milk");
+        assert_eq!(parsed.tags, ["errands"]);
+    }
+
+    #[test]
+    fn rejects_several_unnamed_fields_after_named_ones() {
+        assert_eq!(error_column("notes:From the store,Buy milk,hard"), 1);
+    }
+
+    #[test]
+    fn rejects_name_read_as_field() {
+        assert_eq!(error_column("notes: call mum"), 1);
+    }
+
+    #[test]
+    fn quotes_and_escapes_separators() {
+        let parsed = parse(r#""due: taxes, 2024",notes:a\,b"#);
+
+        assert_eq!(parsed.task.text, "due: taxes, 2024");
+        assert_eq!(parsed.task.notes.as_deref(), Some("a,b"));
+    }
+
+    #[test]
+    fn keeps_semicolons_outside_checklists_and_tags() {
+        let parsed = parse("Call Bob; then Alice,notes:a;b");
+
+        assert_eq!(parsed.task.text, "Call Bob; then Alice");
+        assert_eq!(parsed.task.notes.as_deref(), Some("a;b"));
+    }
+
+    #[test]
+    fn repeated_tags_are_merged() {
+        let parsed = parse("Buy milk,tag:errands,tag:home");
+        assert_eq!(parsed.tags, ["errands", "home"]);
+    }
+
+    #[test]
+    fn points_at_invalid_values() {
+        assert_eq!(error_column("Buy milk,diff:  impossible"), 17);
+        assert_eq!(error_column("Buy milk,due:someday"), 14);
+        assert_eq!(
+            error_column("Buy milk,easy,notes,2024-01-01,check,extra"),
+            38
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_syntax() {
+        assert_eq!(error_column(r#"Buy "milk"#), 5);
+        assert_eq!(error_column(r"Buy milk\"), 9);
+        assert_eq!(error_column("Buy milk,name:Eggs"), 10);
+        assert_eq!(error_column(",easy"), 1);
+    }
+}
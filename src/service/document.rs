@@ -0,0 +1,364 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use time::format_description::well_known::Iso8601;
+
+use super::{
+    types::{SubTask, Task},
+    util::{parse_difficulty, parse_due_date},
+};
+use crate::error::AppError;
+
+const CHECKLIST_HEADING: &str = "## Checklist";
+
+// Comment added on top of a document which failed to parse
+const ERROR_COMMENT: &str = "<!-- habitui:";
+
+/// Write a task as a Markdown document for editing in a text editor:
+///
+/// ```text
+/// # Name of the task
+///
+/// difficulty: Easy
+/// due: 2024-12-24
+///
+/// Notes of the task
+///
+/// ## Checklist
+///
+/// - [x] Done subtask
+/// - [ ] Subtask to do
+/// ```
+pub fn task_to_document(task: &Task) -> String {
+    let due = task
+        .date
+        .and_then(|d| d.format(&Iso8601::DATE).ok())
+        .unwrap_or_default();
+
+    let mut doc = format!(
+        "# {}\n\ndifficulty: {}\ndue: {due}\n\n",
+        task.text.replace('\n', " "),
+        task.difficulty
+    );
+    if let Some(notes) = task.notes.as_ref().filter(|n| !n.is_empty()) {
+        doc.push_str(&format!("{notes}\n\n"));
+    }
+    doc.push_str(&format!("{CHECKLIST_HEADING}\n\n"));
+    for sub in task.checklist.iter().flatten() {
+        let mark = if sub.completed { 'x' } else { ' ' };
+        doc.push_str(&format!("- [{mark}] {}\n", sub.text));
+    }
+
+    doc
+}
+
+fn parse_subtask(line: &str) -> Option<SubTask> {
+    let item = line.strip_prefix("- ").or(line.strip_prefix("* "))?;
+    let (completed, text) = match item.get(..4) {
+        Some("[ ] ") => (false, &item[4..]),
+        Some("[x] " | "[X] ") => (true, &item[4..]),
+        _ => (false, item),
+    };
+
+    Some(SubTask {
+        text: text.trim().to_string(),
+        completed,
+    })
+}
+
+/// Read the changes made to a task from a document written with
+/// task_to_document. Fields of the task which are not in the document are
+/// kept as they are
+pub fn parse_document(doc: &str, base: &Task) -> Result<Task, String> {
+    let mut lines = doc
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim_end()))
+        .filter(|(_, l)| !l.starts_with(ERROR_COMMENT))
+        .peekable();
+    let mut task = base.clone();
+
+    let Some((n, title)) = lines.find(|(_, l)| !l.is_empty()) else {
+        return Err("the document is empty".into());
+    };
+    task.text = match title.strip_prefix("# ").map(str::trim) {
+        Some(text) if !text.is_empty() => text.to_string(),
+        _ => {
+            return Err(format!(
+                "line {n}: expected the name of the task as '# Name'"
+            ))
+        }
+    };
+
+    // Fields are read until the first line which is not a field
+    task.date = None;
+    while let Some((n, line)) = lines
+        .next_if(|(_, l)| l.is_empty() || l.starts_with("difficulty:") || l.starts_with("due:"))
+    {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "difficulty" => {
+                task.difficulty = parse_difficulty(value)
+                    .map_err(|_| format!("line {n}: unknown difficulty '{value}'"))?;
+            }
+            _ if value.is_empty() || value == "none" => task.date = None,
+            _ => {
+                let date = parse_due_date(value)
+                    .map_err(|_| format!("line {n}: invalid due date '{value}'"))?;
+                // Keep the time of day of an unchanged date
+                task.date = match base.date {
+                    Some(d) if d.date() == date.date() => Some(d),
+                    _ => Some(date),
+                };
+            }
+        }
+    }
+
+    // The checklist starts at the last heading, since the notes may contain
+    // a line which reads the same as the heading
+    let mut rest: Vec<(usize, &str)> = lines.collect();
+    let items = match rest
+        .iter()
+        .rposition(|(_, l)| l.eq_ignore_ascii_case(CHECKLIST_HEADING))
+    {
+        Some(heading) => rest.split_off(heading).split_off(1),
+        None => Vec::new(),
+    };
+    let notes: Vec<&str> = rest.into_iter().map(|(_, l)| l).collect();
+    let notes = notes.join("\n").trim().to_string();
+    task.notes = Some(notes).filter(|n| !n.is_empty());
+
+    let mut checklist = Vec::new();
+    for (n, line) in items.into_iter().filter(|(_, l)| !l.trim().is_empty()) {
+        let sub = parse_subtask(line.trim())
+            .ok_or(format!("line {n}: expected a subtask as '- [ ] Subtask'"))?;
+        if !sub.text.is_empty() {
+            checklist.push(sub);
+        }
+    }
+    task.checklist = Some(checklist);
+
+    Ok(task)
+}
+
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Line by line difference between two documents
+pub fn diff_documents(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Length of the longest common subsequence of the lines after i and j
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+
+    diff
+}
+
+/// Difference between a task and its edited version
+pub fn diff_tasks(old: &Task, new: &Task) -> Vec<DiffLine> {
+    diff_documents(&task_to_document(old), &task_to_document(new))
+}
+
+fn editor_command() -> (String, Vec<String>) {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or("vi".into());
+    // Allow editors given with arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace().map(String::from);
+    let program = parts.next().unwrap_or("vi".into());
+
+    (program, parts.collect())
+}
+
+/// Edit a task as a document in $VISUAL or $EDITOR. Invalid documents are
+/// opened again with the error on top until they are valid or saved without
+/// changes. Returns None if the task was not changed
+pub fn edit_in_editor(task: &Task) -> Result<Option<Task>, AppError> {
+    let original = task_to_document(task);
+    // The file gets a random name and is only accessible to the user, so
+    // that other users cannot plant or swap it. It is removed when dropped
+    let file = tempfile::Builder::new()
+        .prefix("habitui-")
+        .suffix(".md")
+        .tempfile()?;
+    let path = file.path();
+    let (program, args) = editor_command();
+
+    let mut doc = original.clone();
+    let edited = loop {
+        fs::write(path, &doc)?;
+        let status = Command::new(&program).args(&args).arg(path).status()?;
+        if !status.success() {
+            Err(AppError::CmdError(format!(
+                "{program} exited with {status}"
+            )))?;
+        }
+
+        let content = fs::read_to_string(path)?;
+        if content == doc {
+            break None;
+        }
+        match parse_document(&content, task) {
+            Ok(edited) => break Some(edited),
+            Err(e) => {
+                let content: Vec<&str> = content
+                    .lines()
+                    .filter(|l| !l.starts_with(ERROR_COMMENT))
+                    .collect();
+                doc = format!(
+                    "{ERROR_COMMENT} {e}. Save without changes to cancel -->\n{}\n",
+                    content.join("\n")
+                );
+            }
+        }
+    };
+
+    Ok(edited.filter(|e| task_to_document(e) != original))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::service::types::Difficulty;
+
+    fn subtask(text: &str, completed: bool) -> SubTask {
+        SubTask {
+            text: text.into(),
+            completed,
+        }
+    }
+
+    fn task() -> Task {
+        Task {
+            text: "Write report".into(),
+            difficulty: Difficulty::HARD,
+            notes: Some("Quarterly numbers\n\n- see last year".into()),
+            date: Some(datetime!(2024-12-24 0:00 UTC)),
+            checklist: Some(vec![subtask("Draft", true), subtask("Review", false)]),
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn round_trips_task() {
+        let task = task();
+        let parsed = parse_document(&task_to_document(&task), &task).unwrap();
+        assert_eq!(parsed, task);
+    }
+
+    #[test]
+    fn keeps_checklist_heading_and_comments_in_notes() {
+        let task = Task {
+            notes: Some("## Checklist\n<!-- not an error -->\nkeep both".into()),
+            ..task()
+        };
+        let parsed = parse_document(&task_to_document(&task), &task).unwrap();
+        assert_eq!(parsed, task);
+    }
+
+    #[test]
+    fn reads_changes() {
+        let doc = "<!-- habitui: line 9: expected a subtask -->
+# Write the report
+
+difficulty: trivial
+due: none
+
+## Checklist
+
+- [X] Draft
+* Proofread
+";
+        let parsed = parse_document(doc, &task()).unwrap();
+
+        assert_eq!(parsed.text, "Write the report");
+        assert_eq!(parsed.difficulty, Difficulty::TRIVIAL);
+        assert_eq!(parsed.date, None);
+        assert_eq!(parsed.notes, None);
+        assert_eq!(
+            parsed.checklist,
+            Some(vec![subtask("Draft", true), subtask("Proofread", false)])
+        );
+    }
+
+    #[test]
+    fn keeps_time_of_unchanged_due_date() {
+        let task = Task {
+            date: Some(datetime!(2024-12-24 18:30 UTC)),
+            ..task()
+        };
+        let parsed = parse_document(&task_to_document(&task), &task).unwrap();
+        assert_eq!(parsed.date, task.date);
+    }
+
+    #[test]
+    fn rejects_invalid_documents() {
+        let task = task();
+        let errors = [
+            ("", "the document is empty"),
+            ("Write report", "line 1: expected the name"),
+            (
+                "# Write report\ndifficulty: impossible",
+                "line 2: unknown difficulty",
+            ),
+            ("# Write report\ndue: someday", "line 2: invalid due date"),
+            (
+                "# Write report\n\n## Checklist\nDraft",
+                "line 4: expected a subtask",
+            ),
+        ];
+        for (doc, error) in errors {
+            let e = parse_document(doc, &task).unwrap_err();
+            assert!(e.starts_with(error), "'{e}' should start with '{error}'");
+        }
+    }
+
+    #[test]
+    fn diffs_lines() {
+        let diff = diff_documents("a\nb\nc", "a\nc\nd");
+        let lines: Vec<String> = diff
+            .iter()
+            .map(|line| match line {
+                DiffLine::Same(l) => format!(" {l}"),
+                DiffLine::Added(l) => format!("+{l}"),
+                DiffLine::Removed(l) => format!("-{l}"),
+            })
+            .collect();
+        assert_eq!(lines, [" a", "-b", " c", "+d"]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use time::{format_description::well_known::Iso8601, OffsetDateTime};
+
+use super::{
+    config::get_config,
+    output::TaskRecord,
+    types::{SubTask, Task},
+    util::{get_task_list, query_completed_tasks, sync_completed_tasks},
+};
+use crate::{
+    error::AppError,
+    types::{ExportFormat, ExportSource, HistoryFilter},
+};
+
+fn format_date(date: Option<OffsetDateTime>) -> String {
+    date.and_then(|d| d.format(&Iso8601::DATE).ok())
+        .unwrap_or_default()
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn write_csv(out: &mut dyn Write, tasks: &[Task]) -> Result<(), AppError> {
+    writeln!(
+        out,
+        "id,text,completed,difficulty,notes,due,completed_at,checklist"
+    )?;
+
+    for task in tasks {
+        let record = TaskRecord::from(task);
+        let checklist = record
+            .checklist
+            .iter()
+            .map(|s| format!("[{}] {}", if s.completed { "x" } else { " " }, s.text))
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        let fields = [
+            record.id.to_string(),
+            record.text,
+            record.completed.to_string(),
+            record.difficulty,
+            record.notes.unwrap_or_default(),
+            format_date(record.due),
+            format_date(record.completed_at),
+            checklist,
+        ];
+        let line = fields
+            .iter()
+            .map(|f| escape_csv(f))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        writeln!(out, "{line}")?;
+    }
+
+    Ok(())
+}
+
+fn write_json(out: &mut dyn Write, tasks: &[Task]) -> Result<(), AppError> {
+    let records: Vec<TaskRecord> = tasks.iter().map(TaskRecord::from).collect();
+    serde_json::to_writer_pretty(&mut *out, &records)?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+fn write_markdown(out: &mut dyn Write, tasks: &[Task]) -> Result<(), AppError> {
+    for task in tasks {
+        let check = if task.completed_at.is_some() {
+            "x"
+        } else {
+            " "
+        };
+        let mut details = vec![task.difficulty.to_string()];
+        if task.date.is_some() {
+            details.push(format!("due {}", format_date(task.date)));
+        }
+        writeln!(out, "- [{check}] {} ({})", task.text, details.join(", "))?;
+
+        if let Some(notes) = task.notes.as_ref().filter(|n| !n.is_empty()) {
+            for line in notes.lines() {
+                writeln!(out, "  > {line}")?;
+            }
+        }
+
+        for SubTask { text, completed } in task.checklist.iter().flatten() {
+            let check = if *completed { "x" } else { " " };
+            writeln!(out, "  - [{check}] {text}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_todotxt(out: &mut dyn Write, tasks: &[Task]) -> Result<(), AppError> {
+    for task in tasks {
+        let mut parts: Vec<String> = Vec::new();
+
+        if task.completed_at.is_some() {
+            parts.push("x".into());
+            parts.push(format_date(task.completed_at));
+        } else {
+            // The highest priority level is written as (A), the next as (B)
+            // and so on
+            let priority_config = &get_config().priority;
+            let priority = task.get_priority();
+            if priority > priority_config.default_priority() {
+                let rank_from_top = (priority_config.highest().0 - priority.0).min(25);
+                parts.push(format!("({})", (b'A' + rank_from_top as u8) as char));
+            }
+        }
+
+        // todo.txt is line based, so multi-line names are flattened
+        parts.push(task.text.replace('\n', " "));
+
+        if task.date.is_some() {
+            parts.push(format!("due:{}", format_date(task.date)));
+        }
+        parts.push(format!(
+            "diff:{}",
+            task.difficulty.to_string().to_lowercase()
+        ));
+
+        writeln!(out, "{}", parts.join(" "))?;
+    }
+
+    Ok(())
+}
+
+pub async fn export_tasks(
+    format: ExportFormat,
+    source: ExportSource,
+    output: Option<PathBuf>,
+) -> Result<(), AppError> {
+    let mut tasks: Vec<Task> = Vec::new();
+
+    if matches!(source, ExportSource::Todos | ExportSource::All) {
+        tasks.append(&mut get_task_list().await?);
+    }
+
+    if matches!(source, ExportSource::History | ExportSource::All) {
+        sync_completed_tasks().await?;
+        tasks.append(&mut query_completed_tasks(&HistoryFilter::default()).await?);
+    }
+
+    let mut out: Box<dyn Write> = match &output {
+        Some(path) if path.as_os_str() != "-" => Box::new(File::create(path)?),
+        _ => Box::new(io::stdout().lock()),
+    };
+
+    match format {
+        ExportFormat::Csv => write_csv(&mut out, &tasks)?,
+        ExportFormat::Json => write_json(&mut out, &tasks)?,
+        ExportFormat::Markdown => write_markdown(&mut out, &tasks)?,
+        ExportFormat::Todotxt => write_todotxt(&mut out, &tasks)?,
+    }
+    out.flush()?;
+
+    if let Some(path) = output.filter(|p| p.as_os_str() != "-") {
+        eprintln!("Exported {} tasks to {}", tasks.len(), path.display());
+    }
+
+    Ok(())
+}
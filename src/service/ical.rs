@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+use time::{macros::format_description, OffsetDateTime};
+use tokio::time::{sleep, Duration};
+
+use super::{
+    config::get_config,
+    output::print_status,
+    types::{Priority, SubTask, Task},
+    util::get_task_list,
+};
+use crate::{error::AppError, util::build_config_path};
+
+// RFC 5545 recommends folding content lines longer than 75 octets
+const MAX_LINE_OCTETS: usize = 75;
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line into CRLF separated chunks without splitting UTF-8
+/// characters
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut line_octets = 0;
+
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        line_octets += c.len_utf8();
+        folded.push(c);
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+/// iCalendar priorities range from 1 (highest) to 9 (lowest). The levels
+/// above the default priority are spread between 1 and 5
+fn ical_priority(priority: Priority) -> usize {
+    let priority_config = &get_config().priority;
+    let default = priority_config.default_priority();
+    if priority <= default {
+        return 9;
+    }
+
+    let n_levels = priority_config.highest().0 - default.0;
+    let rank = priority.0 - default.0;
+    1 + (n_levels - rank) * 4 / n_levels.saturating_sub(1).max(1)
+}
+
+fn build_description(task: &Task) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    if let Some(notes) = task.notes.as_ref().filter(|n| !n.is_empty()) {
+        lines.push(notes.clone());
+    }
+
+    for SubTask { text, completed } in task.checklist.iter().flatten() {
+        let check = if *completed { "x" } else { " " };
+        lines.push(format!("[{check}] {text}"));
+    }
+
+    lines.join("\n")
+}
+
+fn build_vtodo(task: &Task, due: OffsetDateTime, stamp: &str) -> Result<String, AppError> {
+    let date_format = format_description!("[year][month][day]");
+    let due = due
+        .format(&date_format)
+        .map_err(|e| AppError::ServiceError(e.to_string()))?;
+
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}@habitui", task.id),
+        format!("DTSTAMP:{stamp}"),
+        format!("SUMMARY:{}", escape_text(&task.text)),
+        format!("DUE;VALUE=DATE:{due}"),
+        format!("PRIORITY:{}", ical_priority(task.get_priority())),
+        "STATUS:NEEDS-ACTION".to_string(),
+    ];
+
+    let description = build_description(task);
+    if !description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&description)));
+    }
+
+    if let Some(checklist) = task.checklist.as_ref().filter(|c| !c.is_empty()) {
+        let n_completed = checklist.iter().filter(|s| s.completed).count();
+        lines.push(format!(
+            "PERCENT-COMPLETE:{}",
+            n_completed * 100 / checklist.len()
+        ));
+    }
+
+    lines.push("END:VTODO".to_string());
+
+    Ok(lines.iter().map(|l| fold_line(l)).collect())
+}
+
+/// Build an iCalendar document with a VTODO for every task with a due date
+pub fn build_calendar(tasks: &[Task]) -> Result<String, AppError> {
+    let stamp_format = format_description!("[year][month][day]T[hour][minute][second]Z");
+    let stamp = OffsetDateTime::now_utc()
+        .format(&stamp_format)
+        .map_err(|e| AppError::ServiceError(e.to_string()))?;
+
+    let mut calendar = String::new();
+    calendar.push_str(&fold_line("BEGIN:VCALENDAR"));
+    calendar.push_str(&fold_line("VERSION:2.0"));
+    calendar.push_str(&fold_line("PRODID:-//habitui//Habitica TODOs//EN"));
+    calendar.push_str(&fold_line("X-WR-CALNAME:Habitica TODOs"));
+
+    for task in tasks {
+        if let Some(due) = task.date {
+            calendar.push_str(&build_vtodo(task, due, &stamp)?);
+        }
+    }
+
+    calendar.push_str(&fold_line("END:VCALENDAR"));
+
+    Ok(calendar)
+}
+
+async fn write_calendar(path: &Path) -> Result<(), AppError> {
+    let tasks = get_task_list().await?;
+    let calendar = build_calendar(&tasks)?;
+
+    // Write to a temporary file first so that calendar apps never read a
+    // partially written feed
+    let tmp_path = path.with_extension("ics.tmp");
+    fs::write(&tmp_path, calendar)?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+pub async fn generate_ical(output: Option<PathBuf>, watch: Option<u64>) -> Result<(), AppError> {
+    let path = match output {
+        Some(p) => p,
+        None => PathBuf::from(format!("{}/habitica_todos.ics", build_config_path()?)),
+    };
+
+    write_calendar(&path).await?;
+    print_status(&format!("Wrote calendar to {}", path.display()));
+
+    let Some(minutes) = watch else {
+        return Ok(());
+    };
+
+    loop {
+        sleep(Duration::from_secs(minutes.max(1) * 60)).await;
+
+        // Keep the previous feed in place if refreshing fails, e.g. because
+        // of a network hiccup
+        match write_calendar(&path).await {
+            Ok(_) => info!("Refreshed calendar {}", path.display()),
+            Err(e) => error!("Refreshing calendar failed: {e}"),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use inquire::Confirm;
+use serde::Deserialize;
+use time::{macros::format_description, OffsetDateTime, PrimitiveDateTime};
+
+use super::{
+    output::{is_human, print_status, print_tasks},
+    request::fetch_tags,
+    types::{Difficulty, SubTask, Task},
+    util::{parse_difficulty, parse_due_date, post_created_tasks, resolve_tag_ids, RateLimiter},
+};
+use crate::{error::AppError, types::ImportFormat};
+
+/// Task parsed from an import file along with the names of its tags, which
+/// are resolved to Habitica tag IDs only once the import is confirmed
+pub struct ImportedTask {
+    pub task: Task,
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TaskwarriorAnnotation {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct TaskwarriorTask {
+    description: String,
+    status: String,
+    due: Option<String>,
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    annotations: Vec<TaskwarriorAnnotation>,
+}
+
+fn import_error(line: usize, msg: &str) -> AppError {
+    AppError::CmdError(format!("Import failed on line {line}: {msg}"))
+}
+
+fn import_entry_error(entry: usize, msg: &str) -> AppError {
+    AppError::CmdError(format!("Import failed on entry {entry}: {msg}"))
+}
+
+fn parse_todotxt(content: &str) -> Result<Vec<ImportedTask>, AppError> {
+    let mut imported = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let n = i + 1;
+        let mut tokens = line.split_whitespace().peekable();
+
+        match tokens.peek() {
+            // Skip empty and already completed lines
+            None | Some(&"x") => continue,
+            _ => {}
+        }
+
+        let mut task = Task::default();
+        let mut tags = Vec::new();
+        let mut words: Vec<&str> = Vec::new();
+
+        // Priorities are assigned by the priority rules rather than stored in
+        // Habitica, so the priority of a line is skipped
+        if tokens.peek().is_some_and(|t| {
+            let mut chars = t.chars();
+            matches!(
+                (chars.next(), chars.next(), chars.next(), chars.next()),
+                (Some('('), Some(p), Some(')'), None) if p.is_ascii_uppercase()
+            )
+        }) {
+            tokens.next();
+        }
+
+        // Creation date is optional and has no counterpart in Habitica
+        while tokens
+            .peek()
+            .is_some_and(|t| t.len() == 10 && t.chars().all(|c| c.is_ascii_digit() || c == '-'))
+        {
+            tokens.next();
+        }
+
+        for token in tokens {
+            if let Some(tag) = token.strip_prefix('+').or(token.strip_prefix('@')) {
+                if !tag.is_empty() {
+                    tags.push(tag.to_string());
+                    continue;
+                }
+            }
+            match token.split_once(':') {
+                Some(("due", d)) => {
+                    task.date = Some(
+                        parse_due_date(d)
+                            .map_err(|_| import_error(n, &format!("incorrect due date '{d}'")))?,
+                    );
+                }
+                Some(("diff", d)) => {
+                    task.difficulty = parse_difficulty(d)
+                        .map_err(|_| import_error(n, &format!("incorrect difficulty '{d}'")))?;
+                }
+                _ => words.push(token),
+            }
+        }
+
+        if words.is_empty() {
+            Err(import_error(n, "task name is empty"))?;
+        }
+        task.text = words.join(" ");
+
+        imported.push(ImportedTask { task, tags });
+    }
+
+    Ok(imported)
+}
+
+/// Strip a Markdown list item checkbox, returning whether it was checked
+fn parse_checkbox(line: &str) -> Option<(bool, &str)> {
+    let item = line
+        .strip_prefix("- ")
+        .or(line.strip_prefix("* "))
+        .or(line.strip_prefix("+ "))?;
+
+    if let Some(text) = item.strip_prefix("[ ] ") {
+        Some((false, text))
+    } else if let Some(text) = item.strip_prefix("[x] ").or(item.strip_prefix("[X] ")) {
+        Some((true, text))
+    } else {
+        None
+    }
+}
+
+/// Parse the "(Medium, due 2024-01-01)" suffix written by the Markdown export
+fn parse_markdown_details(text: &str) -> Option<(&str, Difficulty, Option<OffsetDateTime>)> {
+    let (name, details) = text.strip_suffix(')')?.rsplit_once(" (")?;
+    let mut difficulty = None;
+    let mut date = None;
+
+    for part in details.split(", ") {
+        if let Some(d) = part.strip_prefix("due ") {
+            date = Some(parse_due_date(d).ok()?);
+        } else {
+            difficulty = Some(parse_difficulty(part).ok()?);
+        }
+    }
+
+    Some((name, difficulty?, date))
+}
+
+fn parse_markdown(content: &str) -> Result<Vec<ImportedTask>, AppError> {
+    let mut imported: Vec<ImportedTask> = Vec::new();
+    // Whether the lines currently being read belong to a task which is kept
+    let mut in_task = false;
+
+    for line in content.lines() {
+        let expanded = line.replace('\t', "    ");
+        let trimmed = expanded.trim_start();
+        let indent = expanded.len() - trimmed.len();
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if indent < 2 {
+            if let Some((checked, text)) = parse_checkbox(trimmed) {
+                // Completed tasks are not imported, nor are their subtasks
+                in_task = !checked;
+                if in_task {
+                    let mut task = Task::default();
+                    match parse_markdown_details(text) {
+                        Some((name, difficulty, date)) => {
+                            task.text = name.to_string();
+                            task.difficulty = difficulty;
+                            task.date = date;
+                        }
+                        None => task.text = text.to_string(),
+                    }
+                    imported.push(ImportedTask {
+                        task,
+                        tags: Vec::new(),
+                    });
+                }
+                continue;
+            }
+            if !trimmed.starts_with('>') {
+                in_task = false;
+                continue;
+            }
+        }
+
+        let Some(ImportedTask { task, .. }) = imported.last_mut().filter(|_| in_task) else {
+            continue;
+        };
+
+        if let Some((completed, text)) = parse_checkbox(trimmed) {
+            task.checklist.get_or_insert_with(Vec::new).push(SubTask {
+                text: text.to_string(),
+                completed,
+            });
+        } else if let Some(text) = trimmed.strip_prefix("- ").or(trimmed.strip_prefix("* ")) {
+            task.checklist.get_or_insert_with(Vec::new).push(SubTask {
+                text: text.to_string(),
+                completed: false,
+            });
+        } else {
+            let text = trimmed.trim_start_matches('>').trim_start();
+            task.notes = Some(match task.notes.take() {
+                Some(notes) => format!("{notes}\n{text}"),
+                None => text.to_string(),
+            });
+        }
+    }
+
+    Ok(imported)
+}
+
+fn parse_taskwarrior_date(input: &str) -> Option<OffsetDateTime> {
+    let format = format_description!("[year][month][day]T[hour][minute][second]Z");
+    PrimitiveDateTime::parse(input, format)
+        .ok()
+        .map(|d| d.assume_utc())
+}
+
+fn parse_taskwarrior(content: &str) -> Result<Vec<ImportedTask>, AppError> {
+    // Taskwarrior exports either a JSON array or one JSON object per line
+    let entries: Vec<TaskwarriorTask> = match serde_json::from_str(content) {
+        Ok(entries) => entries,
+        Err(_) => content
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?,
+    };
+
+    let mut imported = Vec::new();
+
+    for (i, entry) in entries.into_iter().enumerate() {
+        if entry.status != "pending" {
+            continue;
+        }
+
+        let date = match entry.due {
+            Some(due) => Some(parse_taskwarrior_date(&due).ok_or_else(|| {
+                import_entry_error(i + 1, &format!("incorrect due date '{due}'"))
+            })?),
+            None => None,
+        };
+
+        let annotations: Vec<String> = entry
+            .annotations
+            .into_iter()
+            .map(|a| a.description)
+            .collect();
+        // As with todo.txt, the priority of an entry is not imported
+        let notes = Some(annotations.join("\n")).filter(|n| !n.is_empty());
+
+        let mut tags = entry.tags;
+        tags.extend(entry.project);
+
+        imported.push(ImportedTask {
+            task: Task {
+                text: entry.description,
+                notes,
+                date,
+                ..Task::default()
+            },
+            tags,
+        });
+    }
+
+    Ok(imported)
+}
+
+fn guess_format(file: &Path) -> Result<ImportFormat, AppError> {
+    match file.extension().and_then(|e| e.to_str()) {
+        Some("txt") => Ok(ImportFormat::Todotxt),
+        Some("md") | Some("markdown") => Ok(ImportFormat::Markdown),
+        Some("json") => Ok(ImportFormat::Taskwarrior),
+        _ => Err(AppError::CmdError(
+            "Cannot guess import format from file name, use --format".into(),
+        )),
+    }
+}
+
+pub async fn import_tasks(
+    file: PathBuf,
+    format: Option<ImportFormat>,
+    dry_run: bool,
+    yes: bool,
+) -> Result<(), AppError> {
+    let format = match format {
+        Some(f) => f,
+        None => guess_format(&file)?,
+    };
+
+    let content = if file.as_os_str() == "-" {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        buf
+    } else {
+        fs::read_to_string(&file)?
+    };
+
+    let imported = match format {
+        ImportFormat::Todotxt => parse_todotxt(&content)?,
+        ImportFormat::Markdown => parse_markdown(&content)?,
+        ImportFormat::Taskwarrior => parse_taskwarrior(&content)?,
+    };
+
+    if imported.is_empty() {
+        print_status("Nothing to import");
+        return Ok(());
+    }
+
+    if dry_run && !is_human() {
+        let tasks: Vec<Task> = imported.iter().map(|i| i.task.clone()).collect();
+        print_tasks(&tasks)?;
+    } else {
+        for ImportedTask { task, tags } in &imported {
+            print!("{task}");
+            if !tags.is_empty() {
+                println!("Tags: {}", tags.join(", "));
+            }
+            println!();
+        }
+    }
+
+    if dry_run {
+        print_status(&format!(
+            "Dry run: {} tasks would be created",
+            imported.len()
+        ));
+        return Ok(());
+    }
+
+    if !yes
+        && !Confirm::new(format!("Create {} tasks?", imported.len()).as_str())
+            .with_default(false)
+            .prompt()?
+    {
+        return Ok(());
+    }
+
+    // Tag creations count towards the same rate limit as task creations
+    let mut limiter = RateLimiter::default();
+    let mut known_tags = fetch_tags().await?;
+    let mut tasks = Vec::with_capacity(imported.len());
+    for ImportedTask { mut task, tags } in imported {
+        task.tags = resolve_tag_ids(&tags, &mut known_tags, &mut limiter).await?;
+        tasks.push(task);
+    }
+
+    let texts: Vec<String> = tasks.iter().map(|t| t.text.clone()).collect();
+    let results = post_created_tasks(tasks, &mut limiter).await;
+
+    let mut created_tasks: Vec<Task> = Vec::new();
+    let mut n_failed = 0;
+    for (text, res) in texts.iter().zip(results) {
+        match res {
+            Ok(created) => created_tasks.push(created),
+            Err(e) => {
+                n_failed += 1;
+                eprintln!("Failed to create '{text}': {e}");
+            }
+        }
+    }
+
+    if !is_human() {
+        print_tasks(&created_tasks)?;
+    }
+    print_status(&format!(
+        "Imported {} tasks, {n_failed} failed",
+        created_tasks.len()
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::io::{self, IsTerminal};
+
+use inquire::Confirm;
+use time::OffsetDateTime;
+
+use super::{
+    document::{diff_tasks, edit_in_editor, DiffLine},
+    output::{print_status, print_task},
+    request::{complete_task, edit_task, remove_task, reorder_task},
+    resolve::{resolve_subtask, resolve_task},
+    types::{Difficulty, Task},
+    util::{get_task_list, parse_due_date},
+};
+use crate::error::AppError;
+
+/// Changes to make to a task with `habitui edit`
+pub struct TaskChanges {
+    pub name: Option<String>,
+    pub notes: Option<String>,
+    pub due: Option<String>,
+    pub no_due: bool,
+    pub difficulty: Option<Difficulty>,
+}
+
+async fn find_task(query: &str) -> Result<Task, AppError> {
+    let tasks = get_task_list().await?;
+    resolve_task(&tasks, query).cloned()
+}
+
+pub async fn complete(query: String) -> Result<(), AppError> {
+    let mut task = find_task(&query).await?;
+    complete_task(task.id).await?;
+    task.completed_at = Some(OffsetDateTime::now_utc());
+    print_task(&task, &format!("Completed '{}'", task.text))?;
+
+    Ok(())
+}
+
+pub async fn remove(query: String, yes: bool) -> Result<(), AppError> {
+    let task = find_task(&query).await?;
+
+    if !yes
+        && !Confirm::new(&format!("Delete '{}'?", task.text))
+            .with_default(false)
+            .prompt()?
+    {
+        return Ok(());
+    }
+
+    remove_task(task.id).await?;
+    print_task(&task, &format!("Deleted '{}'", task.text))?;
+
+    Ok(())
+}
+
+pub async fn edit(query: String, changes: TaskChanges) -> Result<(), AppError> {
+    let mut task = find_task(&query).await?;
+
+    if let Some(name) = changes.name {
+        if name.trim().is_empty() {
+            Err(AppError::CmdError("Task name cannot be empty".into()))?;
+        }
+        task.text = name;
+    }
+    if let Some(notes) = changes.notes {
+        task.notes = Some(notes).filter(|n| !n.is_empty());
+    }
+    if let Some(due) = changes.due {
+        task.date = Some(parse_due_date(&due)?);
+    }
+    if changes.no_due {
+        task.date = None;
+    }
+    if let Some(difficulty) = changes.difficulty {
+        task.difficulty = difficulty;
+    }
+
+    edit_task(&task).await?;
+    print_task(&task, &format!("Edited: \n{task}"))?;
+
+    Ok(())
+}
+
+/// Edit a task in a text editor and submit it after confirming the changes
+pub async fn edit_external(query: String) -> Result<(), AppError> {
+    let task = find_task(&query).await?;
+    let Some(edited) = edit_in_editor(&task)? else {
+        print_status("No changes");
+        return Ok(());
+    };
+
+    for line in diff_tasks(&task, &edited) {
+        match line {
+            DiffLine::Same(l) => eprintln!("  {l}"),
+            DiffLine::Added(l) => eprintln!("+ {l}"),
+            DiffLine::Removed(l) => eprintln!("- {l}"),
+        }
+    }
+    if io::stdin().is_terminal()
+        && !Confirm::new("Submit changes?")
+            .with_default(true)
+            .prompt()?
+    {
+        return Ok(());
+    }
+
+    edit_task(&edited).await?;
+    print_task(&edited, &format!("Edited: \n{edited}"))?;
+
+    Ok(())
+}
+
+/// Move a task to a 1-based position in the task list
+pub async fn move_to(query: String, position: usize) -> Result<(), AppError> {
+    let task = find_task(&query).await?;
+    reorder_task(task.id, position.saturating_sub(1)).await?;
+    print_task(
+        &task,
+        &format!("Moved '{}' to position {position}", task.text),
+    )?;
+
+    Ok(())
+}
+
+pub async fn check(query: String, subtask: String, uncheck: bool) -> Result<(), AppError> {
+    let mut task = find_task(&query).await?;
+    let index = resolve_subtask(&task, &subtask)?;
+
+    let Some(subtask) = task.checklist.as_mut().and_then(|c| c.get_mut(index)) else {
+        return Ok(());
+    };
+    subtask.completed = !uncheck;
+    let text = subtask.text.clone();
+
+    edit_task(&task).await?;
+    let verb = if uncheck { "Unchecked" } else { "Checked" };
+    print_task(&task, &format!("{verb} '{text}' of '{}'", task.text))?;
+
+    Ok(())
+}
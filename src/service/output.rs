@@ -0,0 +1,353 @@
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use sqlx::types::uuid::Uuid;
+use time::{format_description::well_known::Iso8601, OffsetDateTime};
+
+use super::{
+    types::{SubTask, Task},
+    width::{display_width, pad_to_width},
+};
+use crate::{error::AppError, types::OutputFormat};
+
+static OUTPUT: OnceLock<Output> = OnceLock::new();
+
+/// Normalised representation of a task, independent of the shape of the
+/// Habitica API response. This is the schema of all JSON output, so fields
+/// are only ever added to it
+#[derive(Serialize)]
+pub struct TaskRecord {
+    pub id: Uuid,
+    pub text: String,
+    pub completed: bool,
+    pub difficulty: String,
+    pub priority: String,
+    pub notes: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub due: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub completed_at: Option<OffsetDateTime>,
+    pub checklist: Vec<SubTask>,
+}
+
+impl From<&Task> for TaskRecord {
+    fn from(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            text: task.text.clone(),
+            completed: task.completed_at.is_some(),
+            difficulty: task.difficulty.to_string(),
+            priority: task.get_priority().to_string(),
+            notes: task.notes.clone().filter(|n| !n.is_empty()),
+            due: task.date,
+            completed_at: task.completed_at,
+            checklist: task.checklist.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Id,
+    ShortId,
+    Text,
+    Difficulty,
+    Priority,
+    Notes,
+    Due,
+    CompletedAt,
+    Completed,
+    Checklist,
+    ChecklistDone,
+    ChecklistTotal,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        let field = match name {
+            "id" => Field::Id,
+            "short_id" => Field::ShortId,
+            "text" | "name" => Field::Text,
+            "difficulty" => Field::Difficulty,
+            "priority" => Field::Priority,
+            "notes" => Field::Notes,
+            "due" | "date" => Field::Due,
+            "completed_at" => Field::CompletedAt,
+            "completed" => Field::Completed,
+            "checklist" => Field::Checklist,
+            "checklist_done" => Field::ChecklistDone,
+            "checklist_total" => Field::ChecklistTotal,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn value(&self, record: &TaskRecord) -> String {
+        let n_done = record.checklist.iter().filter(|s| s.completed).count();
+        match self {
+            Field::Id => record.id.to_string(),
+            Field::ShortId => record.id.to_string()[..8].to_string(),
+            Field::Text => record.text.clone(),
+            Field::Difficulty => record.difficulty.clone(),
+            Field::Priority => record.priority.clone(),
+            Field::Notes => record.notes.clone().unwrap_or_default(),
+            Field::Due => format_date(record.due),
+            Field::CompletedAt => format_date(record.completed_at),
+            Field::Completed => record.completed.to_string(),
+            Field::Checklist if record.checklist.is_empty() => String::new(),
+            Field::Checklist => format!("{n_done}/{}", record.checklist.len()),
+            Field::ChecklistDone => n_done.to_string(),
+            Field::ChecklistTotal => record.checklist.len().to_string(),
+        }
+    }
+}
+
+pub enum Piece<F> {
+    Literal(String),
+    Field(F),
+}
+
+/// Parse a template such as "{text} [{difficulty}] due {due}" with the given
+/// parser for field names. Braces are written as {{ and }}
+pub fn parse_template<F>(
+    template: &str,
+    parse_field: impl Fn(&str) -> Option<F>,
+) -> Result<Vec<Piece<F>>, AppError> {
+    let template_error = |msg: String| AppError::CmdError(format!("Invalid template: {msg}"));
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => Err(template_error("unmatched {, write {{ for a brace".into()))?,
+                    }
+                }
+                let field = parse_field(name.trim())
+                    .ok_or_else(|| template_error(format!("unknown field {{{name}}}")))?;
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(Piece::Field(field));
+            }
+            '}' => Err(template_error("unmatched }, write }} for a brace".into()))?,
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+
+    Ok(pieces)
+}
+
+struct Output {
+    format: OutputFormat,
+    template: Vec<Piece<Field>>,
+}
+
+/// Set the output format of the command. A template implies the template
+/// format
+pub fn init_output(format: OutputFormat, template: Option<String>) -> Result<(), AppError> {
+    let format = match (format, &template) {
+        (OutputFormat::Human, Some(_)) => OutputFormat::Template,
+        (OutputFormat::Template, None) => Err(AppError::CmdError(
+            "--output-format template requires --format-template".into(),
+        ))?,
+        (format, _) => format,
+    };
+    let template = match template {
+        Some(t) => parse_template(&t, Field::parse)?,
+        None => Vec::new(),
+    };
+
+    let _ = OUTPUT.set(Output { format, template });
+    Ok(())
+}
+
+fn get_output() -> &'static Output {
+    OUTPUT.get_or_init(|| Output {
+        format: OutputFormat::Human,
+        template: Vec::new(),
+    })
+}
+
+/// Whether output is meant for people rather than other programs
+pub fn is_human() -> bool {
+    matches!(get_output().format, OutputFormat::Human)
+}
+
+/// Whether output is one of the JSON formats
+pub fn is_json() -> bool {
+    matches!(
+        get_output().format,
+        OutputFormat::Json | OutputFormat::Ndjson
+    )
+}
+
+fn format_date(date: Option<OffsetDateTime>) -> String {
+    date.and_then(|d| d.format(&Iso8601::DATE).ok())
+        .unwrap_or_default()
+}
+
+/// Flatten a value on a single line so that each task stays on one line
+fn single_line(value: &str) -> String {
+    value.replace(['\n', '\t'], " ")
+}
+
+fn render_template(template: &[Piece<Field>], record: &TaskRecord) -> String {
+    template
+        .iter()
+        .map(|piece| match piece {
+            Piece::Literal(l) => l.clone(),
+            Piece::Field(f) => single_line(&f.value(record)),
+        })
+        .collect()
+}
+
+const TABLE_FIELDS: [(&str, Field); 6] = [
+    ("ID", Field::ShortId),
+    ("TEXT", Field::Text),
+    ("DIFFICULTY", Field::Difficulty),
+    ("PRIORITY", Field::Priority),
+    ("DUE", Field::Due),
+    ("CHECKLIST", Field::Checklist),
+];
+
+fn write_table(out: &mut dyn Write, records: &[TaskRecord]) -> Result<(), AppError> {
+    let rows: Vec<Vec<String>> = records
+        .iter()
+        .map(|r| {
+            TABLE_FIELDS
+                .iter()
+                .map(|(_, f)| single_line(&f.value(r)))
+                .collect()
+        })
+        .collect();
+
+    let widths: Vec<usize> = TABLE_FIELDS
+        .iter()
+        .enumerate()
+        .map(|(i, (header, _))| {
+            rows.iter()
+                .map(|row| display_width(&row[i]))
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let headers = TABLE_FIELDS.iter().map(|(h, _)| h.to_string());
+    for row in [headers.collect()].iter().chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| pad_to_width(cell, *width))
+            .collect();
+        writeln!(out, "{}", cells.join("  ").trim_end())?;
+    }
+
+    Ok(())
+}
+
+fn write_records(
+    out: &mut dyn Write,
+    records: &[TaskRecord],
+    as_list: bool,
+) -> Result<(), AppError> {
+    let output = get_output();
+    match output.format {
+        OutputFormat::Human => {}
+        OutputFormat::Json if as_list => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Json => {
+            for record in records {
+                serde_json::to_writer_pretty(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Table => write_table(out, records)?,
+        OutputFormat::Plain => {
+            for record in records {
+                let fields = [
+                    Field::Id,
+                    Field::Text,
+                    Field::Difficulty,
+                    Field::Priority,
+                    Field::Due,
+                    Field::CompletedAt,
+                    Field::Checklist,
+                ];
+                let values: Vec<String> = fields
+                    .iter()
+                    .map(|f| single_line(&f.value(record)))
+                    .collect();
+                writeln!(out, "{}", values.join("\t"))?;
+            }
+        }
+        OutputFormat::Template => {
+            for record in records {
+                writeln!(out, "{}", render_template(&output.template, record))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a list of tasks in the selected output format
+pub fn print_tasks(tasks: &[Task]) -> Result<(), AppError> {
+    if is_human() {
+        for task in tasks {
+            println!("{task}");
+        }
+        return Ok(());
+    }
+
+    let records: Vec<TaskRecord> = tasks.iter().map(TaskRecord::from).collect();
+    write_records(&mut io::stdout().lock(), &records, true)
+}
+
+/// Print the task a command acted on. People get the given message while
+/// other formats get the task itself
+pub fn print_task(task: &Task, message: &str) -> Result<(), AppError> {
+    if is_human() {
+        println!("{message}");
+        return Ok(());
+    }
+
+    write_records(&mut io::stdout().lock(), &[TaskRecord::from(task)], false)
+}
+
+/// Print a status message. It goes to stderr unless the output is meant for
+/// people, so that it does not mix with machine readable output
+pub fn print_status(message: &str) {
+    if is_human() {
+        println!("{message}");
+    } else {
+        eprintln!("{message}");
+    }
+}
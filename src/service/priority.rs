@@ -0,0 +1,311 @@
+use std::fmt;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use time::{Duration, OffsetDateTime};
+
+use super::{
+    config::get_config,
+    output::{is_human, print_tasks},
+    resolve::find_matching_tasks,
+    types::{Priority, Task},
+    util::{get_tag_names, get_task_list},
+    width::pad_to_width,
+};
+use crate::error::AppError;
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = Option::<String>::deserialize(deserializer)?;
+    pattern
+        .map(|p| Regex::new(&p).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Inclusive range of values, either bound of which may be left open
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct Window {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Window {
+    fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => write!(f, "between {min} and {max}"),
+            (Some(min), None) => write!(f, "at least {min}"),
+            (None, Some(max)) => write!(f, "at most {max}"),
+            (None, None) => write!(f, "anything"),
+        }
+    }
+}
+
+/// A rule assigning a priority level to every task matching all of the
+/// conditions given in the rule
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityRule {
+    pub name: Option<String>,
+    pub level: String,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub text: Option<Regex>,
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub notes: Option<Regex>,
+    /// Matches if the task has any of the given tags
+    pub tags: Vec<String>,
+    /// Matches if the task has any of the given difficulties
+    pub difficulty: Vec<String>,
+    /// Days until the task is due, negative if the task is overdue
+    pub due_in_days: Option<Window>,
+    /// Percentage of completed checklist items
+    pub checklist_progress: Option<Window>,
+}
+
+impl PriorityRule {
+    fn matches(&self, task: &Task, now: OffsetDateTime) -> bool {
+        if let Some(re) = &self.text {
+            if !re.is_match(&task.text) {
+                return false;
+            }
+        }
+
+        if let Some(re) = &self.notes {
+            if !task.notes.as_ref().is_some_and(|n| re.is_match(n)) {
+                return false;
+            }
+        }
+
+        if !self.tags.is_empty() {
+            let task_tags = get_tag_names(&task.tags);
+            if !self
+                .tags
+                .iter()
+                .any(|t| task_tags.iter().any(|n| n.eq_ignore_ascii_case(t)))
+            {
+                return false;
+            }
+        }
+
+        if !self.difficulty.is_empty()
+            && !self
+                .difficulty
+                .iter()
+                .any(|d| d.eq_ignore_ascii_case(&task.difficulty.to_string()))
+        {
+            return false;
+        }
+
+        if let Some(window) = self.due_in_days {
+            let Some(due) = task.date else {
+                return false;
+            };
+            let days = (due - now) / Duration::DAY;
+            if !window.contains(days) {
+                return false;
+            }
+        }
+
+        if let Some(window) = self.checklist_progress {
+            let Some(checklist) = task.checklist.as_ref().filter(|c| !c.is_empty()) else {
+                return false;
+            };
+            let n_completed = checklist.iter().filter(|s| s.completed).count();
+            let progress = 100.0 * n_completed as f64 / checklist.len() as f64;
+            if !window.contains(progress) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Human readable summary of the conditions of the rule
+    pub fn describe(&self) -> String {
+        let mut conditions: Vec<String> = Vec::new();
+
+        if let Some(re) = &self.text {
+            conditions.push(format!("text matches /{re}/"));
+        }
+        if let Some(re) = &self.notes {
+            conditions.push(format!("notes match /{re}/"));
+        }
+        if !self.tags.is_empty() {
+            conditions.push(format!("tagged {}", self.tags.join(" or ")));
+        }
+        if !self.difficulty.is_empty() {
+            conditions.push(format!("difficulty is {}", self.difficulty.join(" or ")));
+        }
+        if let Some(window) = self.due_in_days {
+            conditions.push(format!("due in {window} days"));
+        }
+        if let Some(window) = self.checklist_progress {
+            conditions.push(format!("checklist progress is {window}%"));
+        }
+
+        if conditions.is_empty() {
+            "always".to_string()
+        } else {
+            conditions.join(", ")
+        }
+    }
+}
+
+impl fmt::Display for PriorityRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "'{name}' ({})", self.describe()),
+            None => write!(f, "{}", self.describe()),
+        }
+    }
+}
+
+/// Priority levels from lowest to highest along with the rules assigning them.
+/// Rules are tried in order and the first matching rule decides the priority
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityConfig {
+    pub levels: Vec<String>,
+    pub default: Option<String>,
+    pub rules: Vec<PriorityRule>,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            levels: vec!["LOW".into(), "MID".into(), "HIGH".into()],
+            default: None,
+            rules: vec![
+                PriorityRule {
+                    name: Some("studying".into()),
+                    level: "MID".into(),
+                    notes: Regex::new("🎓").ok(),
+                    ..PriorityRule::default()
+                },
+                PriorityRule {
+                    name: Some("due within a week".into()),
+                    level: "HIGH".into(),
+                    due_in_days: Some(Window {
+                        min: None,
+                        max: Some(7.0),
+                    }),
+                    ..PriorityRule::default()
+                },
+                PriorityRule {
+                    name: Some("on fire".into()),
+                    level: "HIGH".into(),
+                    notes: Regex::new("🔥").ok(),
+                    ..PriorityRule::default()
+                },
+            ],
+        }
+    }
+}
+
+impl PriorityConfig {
+    fn rank_of(&self, level: &str) -> Option<usize> {
+        self.levels
+            .iter()
+            .position(|l| l.eq_ignore_ascii_case(level))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.levels.is_empty() {
+            return Err("at least one priority level is required".into());
+        }
+        if let Some(level) = &self.default {
+            self.rank_of(level)
+                .ok_or(format!("unknown default priority level '{level}'"))?;
+        }
+        for rule in &self.rules {
+            self.rank_of(&rule.level).ok_or(format!(
+                "unknown priority level '{}' in rule {rule}",
+                rule.level
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub fn default_priority(&self) -> Priority {
+        let rank = self
+            .default
+            .as_ref()
+            .and_then(|l| self.rank_of(l))
+            .unwrap_or(0);
+        Priority(rank)
+    }
+
+    pub fn highest(&self) -> Priority {
+        Priority(self.levels.len().saturating_sub(1))
+    }
+
+    pub fn uses_tags(&self) -> bool {
+        self.rules.iter().any(|r| !r.tags.is_empty())
+    }
+
+    pub fn level_name(&self, priority: Priority) -> &str {
+        self.levels
+            .get(priority.0)
+            .map(|l| l.as_str())
+            .unwrap_or("?")
+    }
+
+    /// Find the priority of a task and the rule which assigned it, if any
+    pub fn evaluate(&self, task: &Task) -> (Priority, Option<&PriorityRule>) {
+        let now = OffsetDateTime::now_utc();
+        for rule in &self.rules {
+            if rule.matches(task, now) {
+                if let Some(rank) = self.rank_of(&rule.level) {
+                    return (Priority(rank), Some(rule));
+                }
+            }
+        }
+
+        (self.default_priority(), None)
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", get_config().priority.level_name(*self))
+    }
+}
+
+/// Print the priority of each task matching the query along with the rule
+/// which assigned it
+pub async fn explain_priorities(query: Option<String>) -> Result<(), AppError> {
+    let tasks = get_task_list().await?;
+    let matching: Vec<&Task> = match query {
+        Some(q) => find_matching_tasks(&tasks, &q),
+        None => tasks.iter().collect(),
+    };
+
+    if matching.is_empty() {
+        Err(AppError::CmdError("No task matches the query".into()))?;
+    }
+
+    // Other formats carry the priority in the task records
+    if !is_human() {
+        let matching: Vec<Task> = matching.into_iter().cloned().collect();
+        return print_tasks(&matching);
+    }
+
+    for task in matching {
+        let (priority, rule) = task.explain_priority();
+        println!("{}{priority}", pad_to_width(&task.text, 48));
+        match rule {
+            Some(rule) => println!("  rule {rule}"),
+            None => println!("  no rule matched, default priority"),
+        }
+    }
+
+    Ok(())
+}
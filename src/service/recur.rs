@@ -0,0 +1,663 @@
+use std::fmt;
+
+use serde_json::json;
+use sqlx::{types::uuid::Uuid, FromRow, Postgres, Transaction};
+use time::{format_description::well_known::Iso8601, Date, Duration, Weekday};
+
+use super::{
+    config::get_config,
+    date::{local_today, parse_weekday},
+    descriptor::TaskDescriptor,
+    output::{is_human, is_json, print_status, print_task, print_tasks},
+    request::{fetch_tags, post_created_task},
+    types::{Tag, Task},
+    util::{create_pg_pool, resolve_tag_ids, RateLimiter},
+};
+use crate::error::AppError;
+
+// Longest gap between two occurrences of a weekday or monthly rule
+const MAX_GAP_DAYS: i64 = 62;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+pub enum MonthDay {
+    /// Day of the month, moved to the last day in shorter months
+    Day(u8),
+    Last,
+}
+
+/// When a recurring TODO is created
+pub enum Rule {
+    /// Every number of days counted from the start date
+    Days(u32),
+    Weekdays(Vec<Weekday>),
+    MonthDay(MonthDay),
+}
+
+fn parse_ordinal(word: &str) -> Option<MonthDay> {
+    let day = match word {
+        "first" => 1,
+        "last" => return Some(MonthDay::Last),
+        _ => {
+            let digits = ["st", "nd", "rd", "th"]
+                .iter()
+                .find_map(|suffix| word.strip_suffix(suffix))
+                .unwrap_or(word);
+            digits.parse().ok()?
+        }
+    };
+    (1..=31).contains(&day).then_some(MonthDay::Day(day))
+}
+
+fn parse_interval(n: &str, unit: &str) -> Option<Rule> {
+    let n: u32 = n.parse().ok().filter(|n| *n > 0)?;
+    let days = match unit {
+        "day" | "days" => n,
+        "week" | "weeks" => n.checked_mul(7)?,
+        _ => return None,
+    };
+    Some(Rule::Days(days))
+}
+
+impl Rule {
+    /// Parse a rule such as `every monday`, `every mon, thu`, `first of month`,
+    /// `15th of month`, `last of month`, `every 10 days` or `every 2 weeks`
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        let input = input.trim().to_lowercase().replace(',', " ");
+        let words: Vec<&str> = input.split_whitespace().collect();
+
+        let rule = match words.as_slice() {
+            ["daily"] | ["every", "day"] => Some(Rule::Days(1)),
+            ["weekly"] | ["every", "week"] => Some(Rule::Days(7)),
+            ["every", "weekday"] => Some(Rule::Weekdays(WEEKDAYS[..5].to_vec())),
+            ["every", n, unit] if n.chars().all(|c| c.is_ascii_digit()) => parse_interval(n, unit),
+            [day, "of", "month"] | [day, "of", "the" | "every" | "each", "month"] => {
+                parse_ordinal(day).map(Rule::MonthDay)
+            }
+            ["every", days @ ..] if !days.is_empty() => days
+                .iter()
+                .filter(|d| **d != "and")
+                .map(|d| parse_weekday(d))
+                .collect::<Option<Vec<Weekday>>>()
+                .map(|mut days| {
+                    days.sort_by_key(|d| d.number_days_from_monday());
+                    days.dedup();
+                    Rule::Weekdays(days)
+                }),
+            _ => None,
+        };
+
+        rule.ok_or_else(|| {
+            AppError::CmdError(format!(
+                "Unknown recurrence rule '{input}', expected e.g. 'every monday', \
+                 'first of month' or 'every 10 days'"
+            ))
+        })
+    }
+
+    fn occurs_on(&self, date: Date, start: Date) -> bool {
+        let last_day = date.month().length(date.year());
+        date >= start
+            && match self {
+                Rule::Days(n) => (date - start).whole_days() % i64::from(*n) == 0,
+                Rule::Weekdays(days) => days.contains(&date.weekday()),
+                Rule::MonthDay(MonthDay::Day(d)) => date.day() == (*d).min(last_day),
+                Rule::MonthDay(MonthDay::Last) => date.day() == last_day,
+            }
+    }
+
+    /// First occurrence after the given date
+    pub fn next_after(&self, after: Date, start: Date) -> Option<Date> {
+        let from = after.next_day()?.max(start);
+        if let Rule::Days(n) = self {
+            let n = i64::from(*n);
+            let periods = ((from - start).whole_days() + n - 1) / n;
+            return start.checked_add(Duration::days(periods * n));
+        }
+
+        (0..=MAX_GAP_DAYS)
+            .filter_map(|i| from.checked_add(Duration::days(i)))
+            .find(|d| self.occurs_on(*d, start))
+    }
+
+    /// Last occurrence on or before the given date
+    pub fn latest_until(&self, until: Date, start: Date) -> Option<Date> {
+        if until < start {
+            return None;
+        }
+        if let Rule::Days(n) = self {
+            let n = i64::from(*n);
+            let periods = (until - start).whole_days() / n;
+            return start.checked_add(Duration::days(periods * n));
+        }
+
+        (0..=MAX_GAP_DAYS)
+            .filter_map(|i| until.checked_sub(Duration::days(i)))
+            .take_while(|d| *d >= start)
+            .find(|d| self.occurs_on(*d, start))
+    }
+}
+
+fn ordinal(day: u8) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{day}{suffix}")
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::Days(1) => write!(f, "every day"),
+            Rule::Days(7) => write!(f, "every week"),
+            Rule::Days(n) if n % 7 == 0 => write!(f, "every {} weeks", n / 7),
+            Rule::Days(n) => write!(f, "every {n} days"),
+            Rule::Weekdays(days) => {
+                let names: Vec<String> =
+                    days.iter().map(|d| d.to_string().to_lowercase()).collect();
+                write!(f, "every {}", names.join(", "))
+            }
+            Rule::MonthDay(MonthDay::Day(1)) => write!(f, "first of month"),
+            Rule::MonthDay(MonthDay::Day(d)) => write!(f, "{} of month", ordinal(*d)),
+            Rule::MonthDay(MonthDay::Last) => write!(f, "last of month"),
+        }
+    }
+}
+
+/// Template which is turned into a TODO whenever its rule is due
+#[derive(FromRow)]
+struct Recurrence {
+    id: i32,
+    template: String,
+    rule: String,
+    /// Values of the template variables as name=value
+    vars: Vec<String>,
+    start_date: Date,
+    last_run: Option<Date>,
+    last_task_id: Option<Uuid>,
+}
+
+impl Recurrence {
+    fn vars(&self, occurrence: Date) -> Vec<(String, String)> {
+        // The date of the occurrence can be used in the template as {date}
+        let date = occurrence.format(&Iso8601::DATE).unwrap_or_default();
+        let mut vars = vec![("date".to_string(), date)];
+        vars.extend(self.vars.iter().filter_map(|var| {
+            var.split_once('=')
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+        }));
+        vars
+    }
+
+    /// Latest occurrence up to today which has not been created yet. Missed
+    /// occurrences before it are skipped
+    fn pending_occurrence(&self, today: Date) -> Result<Option<Date>, AppError> {
+        let occurrence = Rule::parse(&self.rule)?.latest_until(today, self.start_date);
+        Ok(occurrence.filter(|o| self.last_run.is_none_or(|last| *o > last)))
+    }
+
+    /// Occurrence which the next run creates, which is the pending one if
+    /// there is one
+    fn next_occurrence(&self, today: Date) -> Option<Date> {
+        let pending = self.pending_occurrence(today).ok()?;
+        pending.or_else(|| {
+            Rule::parse(&self.rule)
+                .ok()?
+                .next_after(today, self.start_date)
+        })
+    }
+
+    /// TODO of an occurrence, which is due relative to the occurrence rather
+    /// than to the day it is created
+    fn instantiate(&self, occurrence: Date) -> Result<TaskDescriptor, AppError> {
+        get_config().templates.get(&self.template)?.instantiate_on(
+            &self.vars(occurrence),
+            false,
+            occurrence,
+        )
+    }
+}
+
+fn format_date(date: Option<Date>) -> String {
+    date.and_then(|d| d.format(&Iso8601::DATE).ok())
+        .unwrap_or("-".into())
+}
+
+pub async fn add_recurrence(
+    template: String,
+    rule: String,
+    vars: Vec<(String, String)>,
+    start: Option<Date>,
+) -> Result<(), AppError> {
+    let rule = Rule::parse(&rule)?;
+    let start = start.unwrap_or_else(local_today);
+    let recurrence = Recurrence {
+        id: 0,
+        template,
+        rule: rule.to_string(),
+        vars: vars
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect(),
+        start_date: start,
+        last_run: None,
+        last_task_id: None,
+    };
+    // Fail early on unknown templates and missing variables
+    recurrence.instantiate(start)?;
+
+    let pool = create_pg_pool().await?;
+    let id: i32 = sqlx::query_scalar(
+        "INSERT INTO recurrence (template, rule, vars, start_date)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id",
+    )
+    .bind(&recurrence.template)
+    .bind(&recurrence.rule)
+    .bind(&recurrence.vars)
+    .bind(recurrence.start_date)
+    .fetch_one(&pool)
+    .await?;
+
+    print_status(&format!(
+        "Added recurrence {id}: '{}' {rule}, next on {}",
+        recurrence.template,
+        format_date(recurrence.next_occurrence(local_today()))
+    ));
+
+    Ok(())
+}
+
+async fn fetch_recurrences() -> Result<Vec<Recurrence>, AppError> {
+    let pool = create_pg_pool().await?;
+    let recurrences = sqlx::query_as::<_, Recurrence>(
+        "SELECT id, template, rule, vars, start_date, last_run, last_task_id
+        FROM recurrence
+        ORDER BY id",
+    )
+    .fetch_all(&pool)
+    .await?;
+
+    Ok(recurrences)
+}
+
+pub async fn list_recurrences() -> Result<(), AppError> {
+    let recurrences = fetch_recurrences().await?;
+
+    let today = local_today();
+    if is_json() {
+        let records: Vec<_> = recurrences
+            .iter()
+            .map(|r| {
+                json!({
+                    "id": r.id,
+                    "template": r.template,
+                    "rule": r.rule,
+                    "vars": r.vars,
+                    "start": format_date(Some(r.start_date)),
+                    "last_run": r.last_run.map(|d| format_date(Some(d))),
+                    "last_task_id": r.last_task_id,
+                    "next": r.next_occurrence(today).map(|d| format_date(Some(d))),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string(&records)?);
+        return Ok(());
+    }
+
+    if recurrences.is_empty() {
+        print_status("No recurrences, add one with `recur add <template> <rule>`");
+        return Ok(());
+    }
+
+    println!(
+        "{:>4}  {:16}{:24}{:18}LAST RUN",
+        "ID", "TEMPLATE", "RULE", "NEXT"
+    );
+    for r in &recurrences {
+        let next = r.next_occurrence(today);
+        let next = match next {
+            Some(d) if d <= today => format!("{} (due)", format_date(next)),
+            _ => format_date(next),
+        };
+        println!(
+            "{:>4}  {:16}{:24}{:18}{}",
+            r.id,
+            r.template,
+            r.rule,
+            next,
+            format_date(r.last_run)
+        );
+        if !r.vars.is_empty() {
+            println!("      {}", r.vars.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn remove_recurrence(id: i32) -> Result<(), AppError> {
+    let pool = create_pg_pool().await?;
+    let removed = sqlx::query("DELETE FROM recurrence WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await?
+        .rows_affected();
+
+    if removed == 0 {
+        Err(AppError::CmdError(format!("No recurrence with ID {id}")))?;
+    }
+    print_status(&format!("Removed recurrence {id}"));
+
+    Ok(())
+}
+
+/// Create the TODO of an occurrence. The tags of the user are fetched the
+/// first time a template with tags is created
+async fn create_occurrence(
+    recurrence: &Recurrence,
+    occurrence: Date,
+    known_tags: &mut Option<Vec<Tag>>,
+    limiter: &mut RateLimiter,
+) -> Result<Task, AppError> {
+    let TaskDescriptor { mut task, tags } = recurrence.instantiate(occurrence)?;
+    if !tags.is_empty() {
+        let known = match known_tags {
+            Some(known) => known,
+            None => known_tags.insert(fetch_tags().await?),
+        };
+        task.tags = resolve_tag_ids(&tags, known, limiter).await?;
+    }
+
+    limiter.send(|| post_created_task(task.clone())).await
+}
+
+/// Record the TODO created for an occurrence, which releases the lock on
+/// the recurrence
+async fn record_run(
+    mut tx: Transaction<'_, Postgres>,
+    id: i32,
+    occurrence: Date,
+    created: &Task,
+) -> Result<(), AppError> {
+    sqlx::query("UPDATE recurrence SET last_run = $1, last_task_id = $2 WHERE id = $3")
+        .bind(occurrence)
+        .bind(created.id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Create a TODO for every recurrence which is due. An occurrence which was
+/// missed, e.g. because the command did not run that day, is created once
+/// on the next run however many occurrences were missed
+pub async fn run_recurrences(dry_run: bool) -> Result<(), AppError> {
+    let pool = create_pg_pool().await?;
+    let today = local_today();
+    let ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM recurrence ORDER BY id")
+        .fetch_all(&pool)
+        .await?;
+
+    let mut created_tasks = Vec::new();
+    let mut n_failed = 0;
+    let mut known_tags = None;
+    let mut limiter = RateLimiter::default();
+    for id in ids {
+        // The row stays locked until the TODO is created and recorded, and
+        // runs happening at the same time skip it, so that an occurrence is
+        // never created twice
+        let mut tx = pool.begin().await?;
+        let recurrence = sqlx::query_as::<_, Recurrence>(
+            "SELECT id, template, rule, vars, start_date, last_run, last_task_id
+            FROM recurrence
+            WHERE id = $1
+            FOR UPDATE SKIP LOCKED",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(recurrence) = recurrence else {
+            continue;
+        };
+
+        let occurrence = match recurrence.pending_occurrence(today) {
+            Ok(Some(occurrence)) => occurrence,
+            Ok(None) => continue,
+            Err(e) => {
+                n_failed += 1;
+                eprintln!("Failed to run recurrence {id}: {e}");
+                continue;
+            }
+        };
+
+        if dry_run {
+            match recurrence.instantiate(occurrence) {
+                Ok(descriptor) => {
+                    let message = format!(
+                        "Would create for recurrence {id} ({}): \n{}",
+                        format_date(Some(occurrence)),
+                        descriptor.task
+                    );
+                    print_task(&descriptor.task, &message)?;
+                }
+                Err(e) => {
+                    n_failed += 1;
+                    eprintln!("Failed to run recurrence {id}: {e}");
+                }
+            }
+            continue;
+        }
+
+        match create_occurrence(&recurrence, occurrence, &mut known_tags, &mut limiter).await {
+            Ok(created) => {
+                let recorded = record_run(tx, id, occurrence, &created).await;
+                if let Err(e) = recorded {
+                    // The TODO exists, but may be created again by the next
+                    // run since the run was not recorded
+                    n_failed += 1;
+                    eprintln!(
+                        "Created '{}' for recurrence {id} but failed to record it: {e}",
+                        created.text
+                    );
+                    created_tasks.push(created);
+                    continue;
+                }
+
+                if is_human() {
+                    println!(
+                        "Created for recurrence {id} ({}): \n{created}",
+                        format_date(Some(occurrence))
+                    );
+                }
+                created_tasks.push(created);
+            }
+            Err(e) => {
+                n_failed += 1;
+                eprintln!("Failed to run recurrence {id}: {e}");
+            }
+        }
+    }
+
+    if !dry_run {
+        if !is_human() {
+            print_tasks(&created_tasks)?;
+        }
+        print_status(&format!(
+            "Created {} recurring TODOs, {n_failed} failed",
+            created_tasks.len()
+        ));
+    }
+    if n_failed > 0 {
+        Err(AppError::CmdError(format!("{n_failed} recurrences failed")))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    fn rule(input: &str) -> Rule {
+        Rule::parse(input).unwrap()
+    }
+
+    fn recurrence(rule: &str, start_date: Date, last_run: Option<Date>) -> Recurrence {
+        Recurrence {
+            id: 1,
+            template: "template".into(),
+            rule: rule.into(),
+            vars: Vec::new(),
+            start_date,
+            last_run,
+            last_task_id: None,
+        }
+    }
+
+    #[test]
+    fn parses_rules_in_canonical_form() {
+        let cases = [
+            ("daily", "every day"),
+            ("weekly", "every week"),
+            ("every 2 weeks", "every 2 weeks"),
+            ("every 10 days", "every 10 days"),
+            (
+                "every weekday",
+                "every monday, tuesday, wednesday, thursday, friday",
+            ),
+            ("Every Thu, mon and thu", "every monday, thursday"),
+            ("first of month", "first of month"),
+            ("22nd of the month", "22nd of month"),
+            ("last of every month", "last of month"),
+        ];
+        for (input, canonical) in cases {
+            assert_eq!(rule(input).to_string(), canonical);
+            assert_eq!(rule(canonical).to_string(), canonical);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_rules() {
+        for input in [
+            "",
+            "every",
+            "every 0 days",
+            "every funday",
+            "32nd of month",
+            "monthly",
+        ] {
+            assert!(Rule::parse(input).is_err(), "'{input}' should be rejected");
+        }
+    }
+
+    #[test]
+    fn counts_intervals_from_start() {
+        let start = date!(2024 - 01 - 01);
+        let every_10_days = rule("every 10 days");
+
+        assert_eq!(
+            every_10_days.next_after(start, start),
+            Some(date!(2024 - 01 - 11))
+        );
+        assert_eq!(
+            every_10_days.next_after(date!(2023 - 12 - 01), start),
+            Some(start)
+        );
+        assert_eq!(
+            every_10_days.latest_until(date!(2024 - 01 - 25), start),
+            Some(date!(2024 - 01 - 21))
+        );
+        assert_eq!(
+            every_10_days.latest_until(date!(2023 - 12 - 31), start),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_weekdays() {
+        // 2024-01-01 is a Monday
+        let start = date!(2024 - 01 - 01);
+        let mon_thu = rule("every mon, thu");
+
+        assert_eq!(
+            mon_thu.next_after(start, start),
+            Some(date!(2024 - 01 - 04))
+        );
+        assert_eq!(
+            mon_thu.next_after(date!(2024 - 01 - 04), start),
+            Some(date!(2024 - 01 - 08))
+        );
+        assert_eq!(
+            mon_thu.latest_until(date!(2024 - 01 - 07), start),
+            Some(date!(2024 - 01 - 04))
+        );
+    }
+
+    #[test]
+    fn moves_month_days_to_end_of_shorter_months() {
+        let start = date!(2024 - 01 - 01);
+
+        assert_eq!(
+            rule("31st of month").next_after(date!(2024 - 01 - 31), start),
+            Some(date!(2024 - 02 - 29))
+        );
+        assert_eq!(
+            rule("last of month").next_after(date!(2023 - 12 - 31), start),
+            Some(date!(2024 - 01 - 31))
+        );
+        assert_eq!(
+            rule("30th of month").latest_until(date!(2024 - 03 - 15), start),
+            Some(date!(2024 - 02 - 29))
+        );
+    }
+
+    #[test]
+    fn creates_latest_missed_occurrence_once() {
+        let start = date!(2024 - 01 - 01);
+        let today = date!(2024 - 01 - 10);
+
+        let never_run = recurrence("daily", start, None);
+        assert_eq!(never_run.pending_occurrence(today).unwrap(), Some(today));
+
+        let missed = recurrence("every 3 days", start, Some(date!(2024 - 01 - 04)));
+        assert_eq!(
+            missed.pending_occurrence(today).unwrap(),
+            Some(date!(2024 - 01 - 10))
+        );
+
+        let up_to_date = recurrence("every 3 days", start, Some(date!(2024 - 01 - 10)));
+        assert_eq!(up_to_date.pending_occurrence(today).unwrap(), None);
+        assert_eq!(
+            up_to_date.next_occurrence(today),
+            Some(date!(2024 - 01 - 13))
+        );
+    }
+
+    #[test]
+    fn does_not_occur_before_start() {
+        let recurrence = recurrence("daily", date!(2024 - 02 - 01), None);
+        assert_eq!(
+            recurrence
+                .pending_occurrence(date!(2024 - 01 - 10))
+                .unwrap(),
+            None
+        );
+    }
+}
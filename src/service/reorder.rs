@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use sqlx::types::uuid::Uuid;
+
+use super::{
+    config::get_config,
+    output::{is_human, print_status, print_tasks},
+    request::reorder_task,
+    types::{Priority, Task},
+    util::get_task_list,
+    width::pad_to_width,
+};
+use crate::error::AppError;
+
+/// Order in which prioritised tasks are placed among themselves: earliest due
+/// date first, then hardest first. Tasks without a due date come last
+fn compare_secondary(a: &Task, b: &Task) -> Ordering {
+    let due = match (a.date, b.date) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    let difficulty = || {
+        let (a, b): (f64, f64) = (b.difficulty.into(), a.difficulty.into());
+        a.total_cmp(&b)
+    };
+
+    due.then_with(difficulty)
+}
+
+/// Indices of the tasks in descending priority. Tasks above the default
+/// priority are further sorted by due date and difficulty, while the rest
+/// keep their current relative order
+pub fn priority_order(tasks: &[&Task]) -> Vec<usize> {
+    let default = get_config().priority.default_priority();
+    let priorities: Vec<Priority> = tasks.iter().map(|t| t.get_priority()).collect();
+    let mut order: Vec<usize> = (0..tasks.len()).collect();
+
+    // Stable sort so that ties keep the current order
+    order.sort_by(|&a, &b| {
+        priorities[b].cmp(&priorities[a]).then_with(|| {
+            if priorities[a] > default {
+                compare_secondary(tasks[a], tasks[b])
+            } else {
+                Ordering::Equal
+            }
+        })
+    });
+
+    order
+}
+
+/// Sort tasks by descending priority, see `priority_order`
+pub fn sort_by_priority(tasks: &[Task]) -> Vec<Task> {
+    let refs: Vec<&Task> = tasks.iter().collect();
+    priority_order(&refs)
+        .into_iter()
+        .map(|i| tasks[i].clone())
+        .collect()
+}
+
+/// Indices of a longest strictly increasing subsequence of `seq`
+fn longest_increasing_subsequence(seq: &[usize]) -> Vec<usize> {
+    // tails[k] is the index in seq of the smallest tail of an increasing
+    // subsequence of length k + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = vec![None; seq.len()];
+
+    for (i, value) in seq.iter().enumerate() {
+        let k = tails.partition_point(|&t| seq[t] < *value);
+        if k > 0 {
+            prev[i] = Some(tails[k - 1]);
+        }
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut lis = Vec::with_capacity(tails.len());
+    let mut cursor = tails.last().copied();
+    while let Some(i) = cursor {
+        lis.push(i);
+        cursor = prev[i];
+    }
+    lis.reverse();
+
+    lis
+}
+
+/// Plan the moves turning the `current` order into the `desired` one. Each
+/// move is the task ID and the index it is moved to, given the order that
+/// results from the previous moves. The tasks along a longest increasing
+/// subsequence are already in relative order and never moved, which makes
+/// the plan minimal
+pub fn plan_moves(current: &[Uuid], desired: &[Uuid]) -> Vec<(Uuid, usize)> {
+    let desired_positions: Vec<usize> = current
+        .iter()
+        .filter_map(|id| desired.iter().position(|d| d == id))
+        .collect();
+    let in_place: HashSet<Uuid> = longest_increasing_subsequence(&desired_positions)
+        .into_iter()
+        .map(|i| desired[desired_positions[i]])
+        .collect();
+
+    let mut order: Vec<Uuid> = current.to_vec();
+    let mut moves = Vec::new();
+
+    for (i, id) in desired.iter().enumerate() {
+        if in_place.contains(id) {
+            continue;
+        }
+        let Some(from) = order.iter().position(|o| o == id) else {
+            continue;
+        };
+        order.remove(from);
+
+        // Place the task right after its predecessor, which is already in
+        // its final relative position
+        let to = match i {
+            0 => 0,
+            _ => order
+                .iter()
+                .position(|o| *o == desired[i - 1])
+                .map_or(0, |p| p + 1),
+        };
+        order.insert(to, *id);
+        moves.push((*id, to));
+    }
+
+    moves
+}
+
+fn print_order(title: &str, tasks: &[Task]) {
+    println!("{title}");
+    for (i, task) in tasks.iter().enumerate() {
+        let text = pad_to_width(&task.text, 48);
+        println!("{:>4}. {text}{}", i + 1, task.get_priority());
+    }
+}
+
+pub async fn priority_reorder_tasks(dry_run: bool) -> Result<(), AppError> {
+    let tasks = get_task_list().await?;
+    let sorted = sort_by_priority(&tasks);
+
+    let current: Vec<Uuid> = tasks.iter().map(|t| t.id).collect();
+    let desired: Vec<Uuid> = sorted.iter().map(|t| t.id).collect();
+    let moves = plan_moves(&current, &desired);
+
+    if dry_run && !is_human() {
+        print_status(&format!("{} moves planned", moves.len()));
+        return print_tasks(&sorted);
+    }
+
+    if dry_run {
+        print_order("Current order:", &tasks);
+        println!();
+        print_order("Planned order:", &sorted);
+        println!();
+
+        println!("{} moves planned", moves.len());
+        for (id, to) in &moves {
+            if let Some(task) = tasks.iter().find(|t| t.id == *id) {
+                println!("  move '{}' to position {}", task.text, to + 1);
+            }
+        }
+        return Ok(());
+    }
+
+    for (id, to) in &moves {
+        reorder_task(*id, *to).await?;
+    }
+    print_status(&format!("Reordered tasks with {} moves", moves.len()));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(n: u128) -> Vec<Uuid> {
+        (0..n).map(Uuid::from_u128).collect()
+    }
+
+    /// Order resulting from making the moves one after the other, as Habitica
+    /// does
+    fn apply_moves(current: &[Uuid], moves: &[(Uuid, usize)]) -> Vec<Uuid> {
+        let mut order = current.to_vec();
+        for (id, to) in moves {
+            let from = order.iter().position(|o| o == id).unwrap();
+            order.remove(from);
+            order.insert(*to, *id);
+        }
+        order
+    }
+
+    fn permutations(items: &[Uuid]) -> Vec<Vec<Uuid>> {
+        if items.len() <= 1 {
+            return vec![items.to_vec()];
+        }
+        let mut result = Vec::new();
+        for i in 0..items.len() {
+            let mut rest = items.to_vec();
+            let first = rest.remove(i);
+            for mut permutation in permutations(&rest) {
+                permutation.insert(0, first);
+                result.push(permutation);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn same_order_needs_no_moves() {
+        let current = ids(5);
+        assert!(plan_moves(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn moves_single_task_to_top() {
+        let current = ids(4);
+        let desired = vec![current[3], current[0], current[1], current[2]];
+
+        let moves = plan_moves(&current, &desired);
+        assert_eq!(moves, vec![(current[3], 0)]);
+    }
+
+    #[test]
+    fn reverses_order() {
+        let current = ids(4);
+        let desired: Vec<Uuid> = current.iter().rev().copied().collect();
+
+        let moves = plan_moves(&current, &desired);
+        assert_eq!(moves.len(), 3);
+        assert_eq!(apply_moves(&current, &moves), desired);
+    }
+
+    #[test]
+    fn plans_minimal_moves_for_every_order() {
+        let current = ids(5);
+        for desired in permutations(&current) {
+            let moves = plan_moves(&current, &desired);
+            assert_eq!(apply_moves(&current, &moves), desired);
+
+            let positions: Vec<usize> = current
+                .iter()
+                .map(|id| desired.iter().position(|d| d == id).unwrap())
+                .collect();
+            let in_place = longest_increasing_subsequence(&positions).len();
+            assert_eq!(moves.len(), current.len() - in_place);
+        }
+    }
+
+    #[test]
+    fn finds_longest_increasing_subsequence() {
+        let seq = [3, 1, 4, 1, 5, 9, 2, 6];
+        let lis = longest_increasing_subsequence(&seq);
+
+        assert_eq!(lis.len(), 4);
+        assert!(lis.windows(2).all(|w| w[0] < w[1] && seq[w[0]] < seq[w[1]]));
+    }
+}
@@ -9,8 +9,8 @@ use crate::util::build_config_path;
 use crate::{
     error::AppError,
     service::{
-        types::Task,
-        util::{get_json_path, ArrayRes},
+        types::{Tag, Task, User, UserStats},
+        util::{get_json_path, ArrayRes, SingleRes},
     },
 };
 
@@ -57,7 +57,7 @@ pub async fn remove_task(task_id: Uuid) -> Result<Task, AppError> {
     let mut iter = tasks.iter_mut();
     let task = iter
         .position(|t| t.id == task_id)
-        .and_then(|i| Some(tasks.remove(i)))
+        .map(|i| tasks.remove(i))
         .ok_or(AppError::ServiceError(format!(
             "Task with ID: {task_id} not found"
         )))?;
@@ -91,11 +91,34 @@ pub async fn reorder_task(task_id: Uuid, index: usize) -> Result<(), AppError> {
     Ok(())
 }
 
+pub async fn add_task_tag(task_id: Uuid, tag_id: Uuid) -> Result<(), AppError> {
+    let path = get_json_path()?;
+    let data = fs::read_to_string(&path)?;
+    let mut tasks = serde_json::from_str::<ArrayRes<Task>>(data.as_str())?.data;
+
+    let task = tasks
+        .iter_mut()
+        .find(|t| t.id == task_id)
+        .ok_or(AppError::ServiceError(format!(
+            "Task with ID: {task_id} not found"
+        )))?;
+    if !task.tags.contains(&tag_id) {
+        task.tags.push(tag_id);
+    }
+
+    let mut file = File::create(&path)?;
+    let data = serde_json::to_string(&ArrayRes { data: tasks })?;
+    file.write_all(data.as_bytes())?;
+
+    Ok(())
+}
+
 /// Mock version of the fetch_tasks function to avoid unnecessary API calls.
 /// Reads data from ~/.config/habitui/habitica_tasks.json and will fail if such
-/// a file does not exist
+/// a file does not exist. Dailies are read from habitica_dailys.json and
+/// there are none if that file does not exist
 pub async fn fetch_tasks(task_type: &str) -> Result<String, AppError> {
-    if !["todos", "completedTodos"].contains(&task_type) {
+    if !["todos", "completedTodos", "dailys"].contains(&task_type) {
         Err(AppError::ServiceError(format!(
             "Undefined task type: {task_type}"
         )))?;
@@ -104,6 +127,10 @@ pub async fn fetch_tasks(task_type: &str) -> Result<String, AppError> {
     let dir = match task_type {
         "todos" => format!("{path}/habitica_tasks.json"),
         "completedTodos" => format!("{path}/habitica_completed.json"),
+        "dailys" => {
+            let dir = format!("{path}/habitica_dailys.json");
+            return Ok(fs::read_to_string(dir).unwrap_or(r#"{"data":[]}"#.into()));
+        }
         _ => Err(AppError::ServiceError(format!(
             "No matching local JSON for task_type: {task_type}"
         )))?,
@@ -116,3 +143,51 @@ pub async fn fetch_tasks(task_type: &str) -> Result<String, AppError> {
 
     Ok(data)
 }
+
+fn get_tags_path() -> Result<String, AppError> {
+    let dir = build_config_path()?;
+    Ok(format!("{dir}/habitica_tags.json"))
+}
+
+/// Mock version of the fetch_tags function. Reads data from
+/// ~/.config/habitui/habitica_tags.json and returns no tags if such a file
+/// does not exist
+pub async fn fetch_tags() -> Result<Vec<Tag>, AppError> {
+    let Ok(data) = fs::read_to_string(get_tags_path()?) else {
+        return Ok(Vec::new());
+    };
+    let tags = serde_json::from_str::<ArrayRes<Tag>>(data.as_str())?.data;
+
+    Ok(tags)
+}
+
+/// Mock version of the fetch_user_stats function. Reads data from
+/// ~/.config/habitui/habitica_user.json and returns full health if such a
+/// file does not exist
+pub async fn fetch_user_stats() -> Result<UserStats, AppError> {
+    let path = format!("{}/habitica_user.json", build_config_path()?);
+    let Ok(data) = fs::read_to_string(path) else {
+        return Ok(UserStats {
+            hp: 50.0,
+            max_health: 50.0,
+        });
+    };
+    let user = serde_json::from_str::<SingleRes<User>>(data.as_str())?.data;
+
+    Ok(user.stats)
+}
+
+pub async fn post_created_tag(name: &str) -> Result<Tag, AppError> {
+    let mut tags = fetch_tags().await?;
+    let tag = Tag {
+        id: Uuid::now_v7(),
+        name: name.to_string(),
+    };
+    tags.push(tag.clone());
+
+    let mut file = File::create(get_tags_path()?)?;
+    let data = serde_json::to_string(&ArrayRes { data: tags })?;
+    file.write_all(data.as_bytes())?;
+
+    Ok(tag)
+}
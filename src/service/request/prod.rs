@@ -5,15 +5,15 @@ use sqlx::types::uuid::Uuid;
 use crate::{
     error::AppError,
     service::{
-        types::Task,
-        util::{get_env_vars, SingleRes},
+        types::{Tag, Task, User, UserStats},
+        util::{get_env_vars, ArrayRes, SingleRes},
     },
 };
 
 const HABITICA_API_ENDPOINT: &str = "https://habitica.com/api/v3";
 
 fn get_headers() -> Result<HeaderMap, AppError> {
-    let (id, token, xclient, _) = get_env_vars()?;
+    let (id, token, xclient) = get_env_vars()?;
 
     let mut headers = HeaderMap::new();
     headers.insert("x-api-user", HeaderValue::from_str(id.as_str())?);
@@ -106,7 +106,7 @@ pub async fn reorder_task(task_id: Uuid, index: usize) -> Result<(), AppError> {
 /// Fetch all tasks of type <task_type> from Habitica API. For our purposes a "todo"
 /// task is the same as a task in general
 pub async fn fetch_tasks(task_type: &str) -> Result<String, AppError> {
-    if !["todos", "completedTodos"].contains(&task_type) {
+    if !["todos", "completedTodos", "dailys"].contains(&task_type) {
         Err(AppError::ServiceError(format!(
             "Undefined task type: {task_type}"
         )))?;
@@ -123,3 +123,65 @@ pub async fn fetch_tasks(task_type: &str) -> Result<String, AppError> {
 
     Ok(res.text().await?)
 }
+
+/// Add an existing tag to a task. Tags can not be changed by editing the task
+pub async fn add_task_tag(task_id: Uuid, tag_id: Uuid) -> Result<(), AppError> {
+    let client = req::Client::new();
+    let headers = get_headers()?;
+
+    client
+        .post(format!(
+            "{HABITICA_API_ENDPOINT}/tasks/{task_id}/tags/{tag_id}"
+        ))
+        .headers(headers)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Fetch all tags defined by the user
+pub async fn fetch_tags() -> Result<Vec<Tag>, AppError> {
+    let client = req::Client::new();
+    let headers = get_headers()?;
+    let res = client
+        .get(format!("{HABITICA_API_ENDPOINT}/tags"))
+        .headers(headers)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let tags = serde_json::from_str::<ArrayRes<Tag>>(&res.text().await?)?;
+    Ok(tags.data)
+}
+
+/// Fetch the stats of the user, such as health
+pub async fn fetch_user_stats() -> Result<UserStats, AppError> {
+    let client = req::Client::new();
+    let headers = get_headers()?;
+    let res = client
+        .get(format!("{HABITICA_API_ENDPOINT}/user?userFields=stats"))
+        .headers(headers)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let user = serde_json::from_str::<SingleRes<User>>(&res.text().await?)?;
+    Ok(user.data.stats)
+}
+
+pub async fn post_created_tag(name: &str) -> Result<Tag, AppError> {
+    let client = req::Client::new();
+    let headers = get_headers()?;
+    let res = client
+        .post(format!("{HABITICA_API_ENDPOINT}/tags"))
+        .json(&serde_json::json!({ "name": name }))
+        .headers(headers)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let created = serde_json::from_str::<SingleRes<Tag>>(&res.text().await?)?;
+    Ok(created.data)
+}
@@ -0,0 +1,112 @@
+use std::io::{self, IsTerminal};
+
+use inquire::Select;
+
+use super::types::{SubTask, Task};
+use crate::error::AppError;
+
+/// Whether all characters of the query appear in the text in order
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+/// Tasks matching a query, trying the strictest way of matching first: an ID
+/// prefix, then a case insensitive substring of the name and finally the
+/// characters of the query appearing in order in the name
+pub fn find_matching_tasks<'t>(tasks: &'t [Task], query: &str) -> Vec<&'t Task> {
+    let query = query.trim().to_lowercase();
+    let matchers: [&dyn Fn(&Task) -> bool; 3] = [
+        &|t| t.id.to_string().starts_with(&query),
+        &|t| t.text.to_lowercase().contains(&query),
+        &|t| is_subsequence(&query, &t.text.to_lowercase()),
+    ];
+
+    for matcher in matchers {
+        let matching: Vec<&Task> = tasks.iter().filter(|t| matcher(t)).collect();
+        if !matching.is_empty() {
+            return matching;
+        }
+    }
+
+    Vec::new()
+}
+
+/// Pick one of several matches by their labels, prompting the user if
+/// running interactively. Returns the index of the picked match
+fn disambiguate(query: &str, what: &str, labels: Vec<String>) -> Result<usize, AppError> {
+    if !io::stdin().is_terminal() {
+        Err(AppError::CmdError(format!(
+            "'{query}' matches {} {what}s: {}",
+            labels.len(),
+            labels.join(", ")
+        )))?;
+    }
+
+    let selected = Select::new(&format!("'{query}' matches several {what}s:"), labels)
+        .with_vim_mode(true)
+        .raw_prompt()?;
+
+    Ok(selected.index)
+}
+
+/// Resolve a query to a single task
+pub fn resolve_task<'t>(tasks: &'t [Task], query: &str) -> Result<&'t Task, AppError> {
+    let matching = find_matching_tasks(tasks, query);
+
+    match matching.len() {
+        0 => Err(AppError::CmdError(format!("No task matches '{query}'"))),
+        1 => Ok(matching[0]),
+        _ => {
+            // Show the start of the ID to tell apart tasks with the same name
+            let labels: Vec<String> = matching
+                .iter()
+                .map(|t| format!("{} ({})", t.text, &t.id.to_string()[..8]))
+                .collect();
+            Ok(matching[disambiguate(query, "task", labels)?])
+        }
+    }
+}
+
+/// Resolve a query to the index of a subtask of a task, either as a 1-based
+/// position in the checklist or as a part of the subtask text
+pub fn resolve_subtask(task: &Task, query: &str) -> Result<usize, AppError> {
+    let checklist: &[SubTask] = task.checklist.as_deref().unwrap_or_default();
+    if checklist.is_empty() {
+        Err(AppError::CmdError(format!(
+            "'{}' has no checklist",
+            task.text
+        )))?;
+    }
+
+    if let Ok(n) = query.parse::<usize>() {
+        return match n {
+            1.. if n <= checklist.len() => Ok(n - 1),
+            _ => Err(AppError::CmdError(format!(
+                "'{}' has {} subtasks, got {n}",
+                task.text,
+                checklist.len()
+            ))),
+        };
+    }
+
+    let lowercase = query.to_lowercase();
+    let matching: Vec<usize> = (0..checklist.len())
+        .filter(|i| checklist[*i].text.to_lowercase().contains(&lowercase))
+        .collect();
+
+    match matching.len() {
+        0 => Err(AppError::CmdError(format!(
+            "No subtask of '{}' matches '{query}'",
+            task.text
+        ))),
+        1 => Ok(matching[0]),
+        _ => {
+            let labels: Vec<String> = matching
+                .iter()
+                .map(|i| checklist[*i].text.clone())
+                .collect();
+            Ok(matching[disambiguate(query, "subtask", labels)?])
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use super::{
+    config::get_config,
+    date::local_today,
+    output::{is_json, parse_template, Piece},
+    request::{fetch_tasks, fetch_user_stats},
+    types::Daily,
+    util::{get_task_list, ArrayRes},
+};
+use crate::{error::AppError, util::build_config_path};
+
+/// Format and caching of `habitui status`
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusConfig {
+    pub format: String,
+    /// Seconds for which a fetched summary is reused without asking Habitica
+    pub cache_ttl: u64,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            format: "📋 {todos} ⏰ {overdue} 🔥 {high} ☀ {dailies} ❤ {hp}".into(),
+            cache_ttl: 60,
+        }
+    }
+}
+
+impl StatusConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        parse_template(&self.format, Field::parse)
+            .map(|_| ())
+            .map_err(|e| match e {
+                AppError::CmdError(msg) => format!("status format: {msg}"),
+                e => e.to_string(),
+            })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Todos,
+    Overdue,
+    High,
+    Dailies,
+    Hp,
+    MaxHp,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        let field = match name {
+            "todos" => Field::Todos,
+            "overdue" => Field::Overdue,
+            "high" => Field::High,
+            "dailies" => Field::Dailies,
+            "hp" => Field::Hp,
+            "max_hp" => Field::MaxHp,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn value(&self, summary: &StatusSummary) -> String {
+        match self {
+            Field::Todos => summary.todos.to_string(),
+            Field::Overdue => summary.overdue.to_string(),
+            Field::High => summary.high.to_string(),
+            Field::Dailies => summary.dailies.to_string(),
+            // Round up so that any health left never shows as 0
+            Field::Hp => format!("{:.0}", summary.hp.ceil()),
+            Field::MaxHp => format!("{:.0}", summary.max_hp.ceil()),
+        }
+    }
+}
+
+/// Counts shown by `habitui status`, cached in the config dir between runs
+#[derive(Serialize, Deserialize)]
+struct StatusSummary {
+    todos: usize,
+    overdue: usize,
+    high: usize,
+    dailies: usize,
+    hp: f64,
+    max_hp: f64,
+    #[serde(with = "time::serde::rfc3339")]
+    fetched_at: OffsetDateTime,
+}
+
+/// Line written for waybar custom modules in JSON mode
+#[derive(Serialize)]
+struct WaybarStatus<'a> {
+    text: String,
+    tooltip: String,
+    class: &'a str,
+    percentage: u8,
+    todos: usize,
+    overdue: usize,
+    high: usize,
+    dailies: usize,
+    hp: f64,
+    max_hp: f64,
+}
+
+fn get_cache_path() -> Result<String, AppError> {
+    let dir = build_config_path()?;
+    Ok(format!("{dir}/status_cache.json"))
+}
+
+/// The cached summary if it is younger than the configured TTL
+fn read_cache() -> Option<StatusSummary> {
+    let data = fs::read_to_string(get_cache_path().ok()?).ok()?;
+    let summary = serde_json::from_str::<StatusSummary>(&data).ok()?;
+
+    let age = OffsetDateTime::now_utc() - summary.fetched_at;
+    let ttl = Duration::seconds(get_config().status.cache_ttl as i64);
+    (age >= Duration::ZERO && age < ttl).then_some(summary)
+}
+
+async fn fetch_summary() -> Result<StatusSummary, AppError> {
+    let tasks = get_task_list().await?;
+    let raw_dailies = fetch_tasks("dailys").await?;
+    let dailies = serde_json::from_str::<ArrayRes<Daily>>(&raw_dailies)?.data;
+    let stats = fetch_user_stats().await?;
+
+    let priority = &get_config().priority;
+    let highest = priority.highest();
+    // With a single level every task is "high", which tells nothing
+    let has_high = highest != priority.default_priority();
+    let today = local_today();
+
+    Ok(StatusSummary {
+        todos: tasks.len(),
+        overdue: tasks
+            .iter()
+            .filter(|t| t.date.is_some_and(|d| d.date() < today))
+            .count(),
+        high: tasks
+            .iter()
+            .filter(|t| has_high && t.get_priority() == highest)
+            .count(),
+        dailies: dailies.iter().filter(|d| d.is_due && !d.completed).count(),
+        hp: stats.hp,
+        max_hp: stats.max_health,
+        fetched_at: OffsetDateTime::now_utc(),
+    })
+}
+
+fn render(format: Option<&str>, summary: &StatusSummary) -> Result<String, AppError> {
+    let format = format.unwrap_or(&get_config().status.format);
+    let line = parse_template(format, Field::parse)?
+        .iter()
+        .map(|piece| match piece {
+            Piece::Literal(l) => l.clone(),
+            Piece::Field(f) => f.value(summary),
+        })
+        .collect();
+
+    Ok(line)
+}
+
+fn print_summary(format: Option<&str>, summary: &StatusSummary) -> Result<(), AppError> {
+    let text = render(format, summary)?;
+    if !is_json() {
+        println!("{text}");
+        return Ok(());
+    }
+
+    let class = if summary.overdue > 0 { "overdue" } else { "ok" };
+    let percentage = match summary.max_hp {
+        max if max > 0.0 => (summary.hp / max * 100.0).clamp(0.0, 100.0).round() as u8,
+        _ => 0,
+    };
+    let tooltip = format!(
+        "{} TODOs, {} overdue, {} high priority\n{} dailies left today\nHP {:.0}/{:.0}",
+        summary.todos,
+        summary.overdue,
+        summary.high,
+        summary.dailies,
+        summary.hp.ceil(),
+        summary.max_hp.ceil()
+    );
+    let status = WaybarStatus {
+        text,
+        tooltip,
+        class,
+        percentage,
+        todos: summary.todos,
+        overdue: summary.overdue,
+        high: summary.high,
+        dailies: summary.dailies,
+        hp: summary.hp,
+        max_hp: summary.max_hp,
+    };
+    // Waybar reads one JSON object per line
+    println!("{}", serde_json::to_string(&status)?);
+
+    Ok(())
+}
+
+/// Print the status from the cache without starting the async runtime.
+/// Returns false if there is no fresh cache to print from
+pub fn print_cached_status(format: Option<&str>) -> Result<bool, AppError> {
+    match read_cache() {
+        Some(summary) => print_summary(format, &summary).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Fetch a new summary, cache it and print it
+pub async fn show_status(format: Option<String>) -> Result<(), AppError> {
+    let summary = fetch_summary().await?;
+    fs::write(get_cache_path()?, serde_json::to_string(&summary)?)?;
+
+    print_summary(format.as_deref(), &summary)
+}
@@ -0,0 +1,168 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+use time::{format_description::well_known::Iso8601, Date};
+
+use super::{
+    date::local_today,
+    descriptor::TaskDescriptor,
+    output::{parse_template, Piece},
+    types::{SubTask, Task},
+    util::{parse_difficulty, parse_due_date, parse_due_date_from},
+};
+use crate::error::AppError;
+
+/// TODO which is created repeatedly, e.g. a release checklist. The text,
+/// notes and checklist may contain variables such as {version}, which are
+/// given when the template is used. {today} is always today's date
+#[derive(Deserialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskTemplate {
+    pub text: String,
+    pub notes: Option<String>,
+    pub difficulty: Option<String>,
+    /// Due date relative to the day the template is used, e.g. +3d or friday
+    pub due: Option<String>,
+    pub checklist: Vec<String>,
+    pub tags: Vec<String>,
+}
+
+/// Templates by name in the templates section of the config
+#[derive(Deserialize, Default)]
+#[serde(transparent)]
+pub struct TemplateConfig(BTreeMap<String, TaskTemplate>);
+
+impl TemplateConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, template) in &self.0 {
+            template
+                .validate()
+                .map_err(|e| format!("template '{name}': {e}"))?;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<&TaskTemplate, AppError> {
+        self.0.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.names().collect();
+            let available = if names.is_empty() {
+                "no templates are defined in the config".into()
+            } else {
+                format!("available templates are {}", names.join(", "))
+            };
+            AppError::CmdError(format!("Unknown template '{name}', {available}"))
+        })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+/// Replace the variables of a text with their values. Variables without a
+/// value are kept as they are if keep_missing is set
+fn substitute(
+    text: &str,
+    vars: &HashMap<String, String>,
+    keep_missing: bool,
+) -> Result<String, AppError> {
+    let mut filled = String::new();
+    for piece in parse_template(text, |name| Some(name.to_string()))? {
+        match piece {
+            Piece::Literal(l) => filled.push_str(&l),
+            Piece::Field(name) => match vars.get(&name) {
+                Some(value) => filled.push_str(value),
+                None if keep_missing => filled.push_str(&format!("{{{name}}}")),
+                None => Err(AppError::CmdError(format!(
+                    "No value for {{{name}}}, give one with --var {name}=<value>"
+                )))?,
+            },
+        }
+    }
+    Ok(filled)
+}
+
+impl TaskTemplate {
+    fn validate(&self) -> Result<(), String> {
+        if self.text.trim().is_empty() {
+            return Err("text is empty".into());
+        }
+        for text in [Some(&self.text), self.notes.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(&self.checklist)
+        {
+            parse_template(text, |_| Some(())).map_err(|e| match e {
+                AppError::CmdError(msg) => msg,
+                e => e.to_string(),
+            })?;
+        }
+        if let Some(difficulty) = &self.difficulty {
+            parse_difficulty(difficulty)
+                .map_err(|_| format!("unknown difficulty '{difficulty}'"))?;
+        }
+        if let Some(due) = &self.due {
+            parse_due_date(due).map_err(|_| format!("invalid due date '{due}'"))?;
+        }
+        Ok(())
+    }
+
+    /// Create a TODO from the template with the given values of its
+    /// variables. Variables without a value are an error unless keep_missing
+    /// is set, in which case they are left in the text to be filled in
+    pub fn instantiate(
+        &self,
+        vars: &[(String, String)],
+        keep_missing: bool,
+    ) -> Result<TaskDescriptor, AppError> {
+        self.instantiate_on(vars, keep_missing, local_today())
+    }
+
+    /// Create a TODO from the template as if on the given day, which is the
+    /// value of {today} and from which a relative due date such as +3d is
+    /// counted
+    pub fn instantiate_on(
+        &self,
+        vars: &[(String, String)],
+        keep_missing: bool,
+        day: Date,
+    ) -> Result<TaskDescriptor, AppError> {
+        let mut vars: HashMap<String, String> = vars.iter().cloned().collect();
+        if let Ok(today) = day.format(&Iso8601::DATE) {
+            vars.entry("today".into()).or_insert(today);
+        }
+        let fill = |text: &str| substitute(text, &vars, keep_missing);
+
+        let checklist = self
+            .checklist
+            .iter()
+            .map(|item| {
+                Ok(SubTask {
+                    text: fill(item)?,
+                    completed: false,
+                })
+            })
+            .collect::<Result<Vec<SubTask>, AppError>>()?;
+
+        let task = Task {
+            text: fill(&self.text)?,
+            notes: self.notes.as_deref().map(fill).transpose()?,
+            difficulty: match &self.difficulty {
+                Some(d) => parse_difficulty(d)?,
+                None => Task::default().difficulty,
+            },
+            date: self
+                .due
+                .as_deref()
+                .map(|due| parse_due_date_from(due, day))
+                .transpose()?,
+            checklist: Some(checklist).filter(|c| !c.is_empty()),
+            ..Task::default()
+        };
+
+        Ok(TaskDescriptor {
+            task,
+            tags: self.tags.clone(),
+        })
+    }
+}
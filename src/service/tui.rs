@@ -2,16 +2,21 @@ use std::io::stdout;
 
 use ratatui::prelude::{CrosstermBackend, Terminal};
 
-use crate::error::AppError;
+use crate::{error::AppError, service::document::edit_in_editor};
 
 use app::Habitui;
 use event::{Event, EventHandler};
 use handler::handle_key_events;
+use session::load_session;
 use tui::Tui;
 
 pub mod app;
 pub mod event;
 pub mod handler;
+pub mod markdown;
+pub mod pomodoro;
+pub mod session;
+#[allow(clippy::module_inception)]
 pub mod tui;
 pub mod ui;
 pub mod util;
@@ -21,6 +26,7 @@ pub mod widgets;
 pub async fn run() -> Result<(), AppError> {
     // Create an application.
     let mut app = Habitui::default();
+    app.grid_state.layout = load_session().layout;
 
     // Initialize the terminal user interface.
     let events = EventHandler::new(250);
@@ -39,6 +45,15 @@ pub async fn run() -> Result<(), AppError> {
             Event::Mouse(_) => {}
             Event::Resize(_, _) => {}
         }
+
+        // Edit the requested task in a text editor, which needs the terminal
+        if let Some(task) = app.external_edit.take() {
+            tui.suspend().await?;
+            let original = task.clone();
+            let edited = tokio::task::spawn_blocking(move || edit_in_editor(&task)).await?;
+            tui.resume()?;
+            app.handle_external_edit(original, edited);
+        }
     }
 
     // Exit the user interface.
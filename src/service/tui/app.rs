@@ -1,14 +1,31 @@
+use std::collections::HashMap;
+use std::mem;
+use std::slice;
+use std::sync::Arc;
+
+use super::pomodoro::{log_session, FocusSession, FocusTask, PomodoroState};
+use super::widgets::detail::DetailState;
 use super::widgets::editor::EditorState;
 use super::widgets::grid::TaskGridState;
 
+use ratatui::widgets::ListState;
+use sqlx::types::uuid::Uuid;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::Mutex;
 use tokio::task::JoinSet;
+use tui_textarea::TextArea;
 
+use crate::error::AppError;
 use crate::service::{
-    request::{complete_task, edit_task, post_created_task, remove_task, reorder_task},
-    types::{Action, Task},
-    util::get_task_list,
+    config::get_config,
+    descriptor::TaskDescriptor,
+    request::{
+        add_task_tag, complete_task, edit_task, fetch_tags, post_created_task, remove_task,
+        reorder_task,
+    },
+    types::{Action, SubTask, Task},
+    util::{get_task_list, resolve_tag_ids, RateLimiter},
 };
 
 #[derive(PartialEq)]
@@ -16,29 +33,64 @@ pub enum AppState {
     List,
     Exit,
     Editor,
+    Diff,
+    Detail,
+    TagInput,
+    TemplatePicker,
 }
 
 pub struct Habitui<'e> {
     pub state: AppState,
     pub grid_state: TaskGridState,
     pub editor_state: Option<EditorState<'e>>,
+    pub detail_state: DetailState,
     pub tx: Sender<Vec<(Task, Action)>>,
     pub rx: Receiver<Vec<(Task, Action)>>,
+    /// Failures of background requests, shown as debug messages
+    pub log_tx: Sender<String>,
+    pub log_rx: Receiver<String>,
+    /// Name of the tag to add to the picked tasks
+    pub tag_input: TextArea<'e>,
+    /// TODO created from a template once its tags are resolved
+    pub template_tx: Sender<Task>,
+    pub template_rx: Receiver<Task>,
+    pub template_picker: ListState,
+    /// Spaces out the tags created by background requests
+    pub limiter: Arc<Mutex<RateLimiter>>,
+    pub pomodoro: PomodoroState,
     pub should_refresh_tasks: bool,
     pub log_debug: Option<(String, u32)>,
+    /// Task to edit in a text editor on the next loop of the TUI
+    pub external_edit: Option<Task>,
+    /// Task before and after editing it in a text editor, waiting for the
+    /// changes to be confirmed
+    pub pending_edit: Option<(Task, Task)>,
 }
 
 impl Default for Habitui<'_> {
     fn default() -> Self {
         let (tx, rx) = mpsc::channel::<Vec<(Task, Action)>>(1);
+        let (log_tx, log_rx) = mpsc::channel::<String>(1);
+        let (template_tx, template_rx) = mpsc::channel::<Task>(1);
         Self {
             state: AppState::List,
             grid_state: TaskGridState::default(),
             tx,
             rx,
+            log_tx,
+            log_rx,
+            tag_input: TextArea::default(),
+            template_tx,
+            template_rx,
+            template_picker: ListState::default(),
+            limiter: Arc::default(),
+            pomodoro: PomodoroState::default(),
             should_refresh_tasks: true,
             editor_state: None,
+            detail_state: DetailState::default(),
             log_debug: None,
+            external_edit: None,
+            pending_edit: None,
         }
     }
 }
@@ -52,12 +104,12 @@ impl Habitui<'_> {
             tokio::spawn(async move {
                 if let Ok(tasks_res) = get_task_list().await {
                     let tasks_msg = tasks_res.into_iter().map(|t| (t, Action::Create)).collect();
-                    if let Err(_) = tx.send(tasks_msg).await {}
+                    let _ = tx.send(tasks_msg).await;
                 }
             });
         }
         if let Ok(tasks) = self.rx.try_recv() {
-            if self.grid_state.task_items.len() == 0 {
+            if self.grid_state.task_items.is_empty() {
                 self.grid_state.task_items = tasks.into_iter().map(|(t, _)| t).collect();
             } else {
                 let mut updates: Vec<(Option<usize>, (Task, Action))> = Vec::new();
@@ -89,7 +141,10 @@ impl Habitui<'_> {
                     }
                 }
                 self.grid_state.modifications.clear();
+                self.grid_state.new_tags.clear();
             }
+            self.grid_state.server_order =
+                self.grid_state.task_items.iter().map(|t| t.id).collect();
         }
     }
 
@@ -99,55 +154,221 @@ impl Habitui<'_> {
         tokio::spawn(async move {
             if task.id.is_nil() {
                 if let Ok(create_res) = post_created_task(task).await {
-                    if let Err(_) = tx.send(vec![(create_res, Action::Create)]).await {}
+                    let _ = tx.send(vec![(create_res, Action::Create)]).await;
                 }
             } else {
                 if let Ok(update_res) = edit_task(&task).await {
-                    if let Err(_) = tx
+                    let _ = tx
                         .send(vec![(update_res.clone(), Action::Edit(task))])
-                        .await
-                    {}
+                        .await;
                 }
             }
         });
     }
 
+    pub fn handle_external_edit(&mut self, original: Task, edited: Result<Option<Task>, AppError>) {
+        match edited {
+            Ok(Some(task)) => {
+                self.pending_edit = Some((original, task));
+                self.state = AppState::Diff;
+            }
+            Ok(None) => {}
+            Err(e) => self.log_debug = Some((e.to_string(), 20)),
+        }
+    }
+
+    /// Submit or discard the changes made in a text editor
+    pub fn handle_pending_edit(&mut self, submit: bool) {
+        if let Some((_, task)) = self.pending_edit.take() {
+            if submit {
+                self.handle_submit_task(task);
+            }
+        }
+        self.state = AppState::List;
+    }
+
+    pub fn open_tag_input(&mut self) {
+        if self.grid_state.get_targets().is_empty() {
+            return;
+        }
+        self.tag_input = TextArea::default();
+        self.tag_input.set_placeholder_text("Tag name");
+        self.state = AppState::TagInput;
+    }
+
+    /// Add the entered tag to the picked tasks as a modification
+    pub fn handle_submit_tag(&mut self) {
+        let name = self.tag_input.lines().join(" ").trim().to_string();
+        let targets = self.grid_state.get_targets();
+        self.grid_state.clear_multi_select();
+        self.state = AppState::List;
+        if name.is_empty() {
+            return;
+        }
+
+        self.grid_state.add_tag(&targets, name);
+    }
+
+    fn handle_log_messages(&mut self) {
+        if let Ok(msg) = self.log_rx.try_recv() {
+            self.log_debug = Some((msg, 20));
+        }
+    }
+
+    /// Open the editor with a new TODO filled in from the given one
+    fn open_prefilled_editor(&mut self, task: Task) {
+        let mut editor = EditorState::new(Some(&task));
+        editor.is_modified = true;
+        self.editor_state = Some(editor);
+        self.state = AppState::Editor;
+    }
+
+    /// Open the editor with a copy of the selected task
+    pub fn handle_duplicate_task(&mut self) {
+        let Some(task) = self.grid_state.get_selected() else {
+            return;
+        };
+        let (task, _) = self.grid_state.with_modifications(task);
+        let checklist = task.checklist.clone().map(|checklist| {
+            checklist
+                .into_iter()
+                .map(|sub| SubTask {
+                    completed: false,
+                    ..sub
+                })
+                .collect()
+        });
+        let copy = Task {
+            text: task.text.clone(),
+            notes: task.notes.clone(),
+            difficulty: task.difficulty,
+            date: task.date,
+            checklist,
+            tags: task.tags.clone(),
+            ..Task::default()
+        };
+        self.open_prefilled_editor(copy);
+    }
+
+    pub fn open_template_picker(&mut self) {
+        if get_config().templates.names().next().is_none() {
+            self.log_debug = Some(("No templates are defined in the config".into(), 20));
+            return;
+        }
+        self.template_picker.select(Some(0));
+        self.state = AppState::TemplatePicker;
+    }
+
+    /// Create a TODO from the picked template and open it in the editor.
+    /// Variables of the template are left in the text to be filled in
+    pub fn handle_pick_template(&mut self) {
+        self.state = AppState::List;
+        let templates = &get_config().templates;
+        let Some(name) = self
+            .template_picker
+            .selected()
+            .and_then(|i| templates.names().nth(i))
+        else {
+            return;
+        };
+        let descriptor = match templates.get(name).and_then(|t| t.instantiate(&[], true)) {
+            Ok(descriptor) => descriptor,
+            Err(e) => {
+                self.log_debug = Some((e.to_string(), 20));
+                return;
+            }
+        };
+
+        if descriptor.tags.is_empty() {
+            self.open_prefilled_editor(descriptor.task);
+            return;
+        }
+        let template_tx = self.template_tx.clone();
+        let limiter = self.limiter.clone();
+        tokio::spawn(async move {
+            let TaskDescriptor { mut task, tags } = descriptor;
+            if let Ok(mut known) = fetch_tags().await {
+                let mut limiter = limiter.lock().await;
+                task.tags = resolve_tag_ids(&tags, &mut known, &mut limiter)
+                    .await
+                    .unwrap_or_default();
+            }
+            let _ = template_tx.send(task).await;
+        });
+    }
+
+    fn handle_template_task(&mut self) {
+        if self.state != AppState::List {
+            return;
+        }
+        if let Ok(task) = self.template_rx.try_recv() {
+            self.open_prefilled_editor(task);
+        }
+    }
+
     pub fn handle_submit_modifications(&mut self) {
         let tx = self.tx.clone();
+        let log_tx = self.log_tx.clone();
         let tasks = self.grid_state.task_items.clone();
         let task_edits = self.grid_state.modifications.clone();
+        let new_tags = mem::take(&mut self.grid_state.new_tags);
+        let limiter = self.limiter.clone();
+
+        // Moves are made one at a time, since each move is relative to the
+        // order resulting from the previous ones
+        let moves = self.grid_state.pending_moves();
 
         tokio::spawn(async move {
+            for (id, to) in moves {
+                if reorder_task(id, to).await.is_err() {
+                    break;
+                }
+            }
+
+            let tag_ids = Arc::new(resolve_new_tags(new_tags, &log_tx, &limiter).await);
+
             let mut handle_set: JoinSet<(Task, Vec<Action>)> = JoinSet::new();
             for (id, mods) in task_edits {
                 let task = tasks.iter().find(|t| t.id == id).unwrap().clone();
+                let tag_ids = tag_ids.clone();
                 handle_set.spawn(async move {
                     let mut updates: (Task, Vec<Action>) = (task, Vec::new());
                     let mut destructive_update: Option<Action> = None;
                     for m in mods {
                         match m {
-                            Action::Edit(m_task) => {
-                                let _ = edit_task(&m_task).await.and_then(|res| {
+                            Action::Edit(mut m_task) => {
+                                // Tags which could not be resolved are left out
+                                let mut tags: Vec<Uuid> = Vec::new();
+                                for tag in &m_task.tags {
+                                    let tag = tag_ids.get(tag).copied().unwrap_or(Some(*tag));
+                                    if let Some(tag) = tag.filter(|t| !tags.contains(t)) {
+                                        tags.push(tag);
+                                    }
+                                }
+                                m_task.tags = tags;
+
+                                let old_tags = &updates.0.tags;
+                                let new_tags = m_task.tags.iter().filter(|t| !old_tags.contains(t));
+                                for tag in new_tags {
+                                    let _ = add_task_tag(id, *tag).await;
+                                }
+                                let _ = edit_task(&m_task).await.map(|res| {
                                     updates.0 = res.clone();
                                     updates.1.push(Action::Edit(res.clone()));
-                                    Ok(())
                                 });
                             }
                             Action::ToggleComplete | Action::Remove => {
                                 destructive_update = Some(m);
                             }
-                            Action::Reorder(o) => {
-                                let _ = reorder_task(id.clone(), o.1).await;
-                                updates.1.push(Action::Reorder(o));
-                            }
+                            Action::Reorder(o) => updates.1.push(Action::Reorder(o)),
                             _ => {}
                         }
                     }
                     if let Some(u) = destructive_update {
                         if u == Action::Remove {
-                            let _ = remove_task(id.clone()).await;
+                            let _ = remove_task(id).await;
                         } else {
-                            let _ = complete_task(id.clone()).await;
+                            let _ = complete_task(id).await;
                         }
                         updates.1.push(u)
                     }
@@ -162,7 +383,76 @@ impl Habitui<'_> {
                     }
                 }
             }
-            if let Err(_) = tx.send(updates).await {}
+            let _ = tx.send(updates).await;
+        });
+    }
+
+    /// Start a focus session on the selected task, or pause or resume the
+    /// running timer
+    pub fn handle_focus(&mut self) {
+        if self.pomodoro.is_active() {
+            self.pomodoro.toggle_pause();
+            return;
+        }
+        let Some(task) = self.grid_state.get_selected() else {
+            return;
+        };
+        let (task, _) = self.grid_state.with_modifications(task);
+        let subtask = self.grid_state.selected_sub.and_then(|i| {
+            let checklist = task.checklist.as_ref()?;
+            checklist.get(i).map(|s| s.text.clone())
+        });
+        self.pomodoro.start_focus(FocusTask {
+            id: task.id,
+            text: task.text.clone(),
+            subtask,
+        });
+    }
+
+    pub fn handle_stop_focus(&mut self) {
+        if let Some(session) = self.pomodoro.stop() {
+            self.log_focus_session(session);
+        }
+    }
+
+    fn log_focus_session(&self, session: FocusSession) {
+        let log_tx = self.log_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = log_session(session).await {
+                let _ = log_tx
+                    .send(format!("Logging focus session failed: {e}"))
+                    .await;
+            }
+        });
+    }
+
+    /// Mark the item focused on as completed once a focus session runs out,
+    /// to be submitted like any other modification
+    fn handle_pomodoro(&mut self) {
+        let Some(session) = self.pomodoro.tick() else {
+            return;
+        };
+        if get_config().pomodoro.mark_completed {
+            let sub = session.task.subtask.as_deref();
+            self.grid_state.complete_item(session.task.id, sub);
+        }
+        self.log_focus_session(session);
+    }
+
+    /// Commit the order of the current grid view to Habitica. The moves are
+    /// made one at a time, since each move is relative to the order resulting
+    /// from the previous ones
+    pub fn handle_commit_view(&mut self) {
+        let Some(moves) = self.grid_state.commit_view() else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            for (id, to) in moves {
+                if reorder_task(id, to).await.is_err() {
+                    break;
+                }
+            }
         });
     }
 
@@ -185,12 +475,55 @@ impl Habitui<'_> {
     pub fn tick(&mut self) {
         self.handle_fetch_tasks();
 
-        self.editor_state.as_mut().map(|s| {
+        if let Some(s) = self.editor_state.as_mut() {
             s.decay_mod_key();
-        });
+        }
 
         self.grid_state.decay_mod_key();
 
+        self.handle_log_messages();
+
+        self.handle_template_task();
+
+        self.handle_pomodoro();
+
         self.decay_debug_msg();
     }
 }
+
+/// Look up the tags added in the TUI by name, creating the ones which do not
+/// exist yet. Returns the ID of the tag standing behind each placeholder ID,
+/// or None if the tag could not be resolved
+async fn resolve_new_tags(
+    new_tags: HashMap<Uuid, String>,
+    log_tx: &Sender<String>,
+    limiter: &Mutex<RateLimiter>,
+) -> HashMap<Uuid, Option<Uuid>> {
+    if new_tags.is_empty() {
+        return HashMap::new();
+    }
+    let mut known = match fetch_tags().await {
+        Ok(known) => known,
+        Err(e) => {
+            let _ = log_tx.send(format!("Fetching tags failed: {e}")).await;
+            return new_tags.into_keys().map(|id| (id, None)).collect();
+        }
+    };
+
+    let mut limiter = limiter.lock().await;
+    let mut tag_ids = HashMap::new();
+    for (placeholder, name) in new_tags {
+        let tag = match resolve_tag_ids(slice::from_ref(&name), &mut known, &mut limiter).await {
+            Ok(ids) => ids.first().copied(),
+            Err(e) => {
+                let _ = log_tx
+                    .send(format!("Adding tag '{name}' failed: {e}"))
+                    .await;
+                None
+            }
+        };
+        tag_ids.insert(placeholder, tag);
+    }
+
+    tag_ids
+}
@@ -14,8 +14,10 @@ pub enum Event {
     /// Key press.
     Key(KeyEvent),
     /// Mouse click/scroll.
+    #[allow(dead_code)]
     Mouse(MouseEvent),
     /// Terminal resize.
+    #[allow(dead_code)]
     Resize(u16, u16),
 }
 
@@ -29,12 +31,14 @@ pub struct EventHandler {
     receiver: mpsc::UnboundedReceiver<Event>,
     /// Event handler thread.
     handler: tokio::task::JoinHandle<()>,
+    /// Tick rate in milliseconds.
+    tick_rate: u64,
 }
 
 impl EventHandler {
     /// Constructs a new instance of [`EventHandler`].
-    pub fn new(tick_rate: u64) -> Self {
-        let tick_rate = Duration::from_millis(tick_rate);
+    pub fn new(tick_rate_ms: u64) -> Self {
+        let tick_rate = Duration::from_millis(tick_rate_ms);
         let (sender, receiver) = mpsc::unbounded_channel();
         let _sender = sender.clone();
         let handler = tokio::spawn(async move {
@@ -78,9 +82,25 @@ impl EventHandler {
             sender,
             receiver,
             handler,
+            tick_rate: tick_rate_ms,
         }
     }
 
+    /// Stops reading terminal events, so that another program can read the
+    /// input of the terminal.
+    pub async fn stop(&mut self) {
+        self.handler.abort();
+        // Wait for the event stream to be dropped
+        let _ = (&mut self.handler).await;
+    }
+
+    /// Starts reading terminal events again after [`stop`].
+    ///
+    /// [`stop`]: EventHandler::stop
+    pub fn restart(&mut self) {
+        *self = Self::new(self.tick_rate);
+    }
+
     /// Receive the next event from the handler thread.
     ///
     /// This function will always block the current thread if
@@ -5,19 +5,95 @@ use crate::error::AppError;
 
 use super::{
     app::{AppState, Habitui},
+    session::{save_session, Session},
     util::Direction,
-    widgets::editor::{EditorMode, EditorState},
+    widgets::{
+        detail::DetailState,
+        editor::{EditorMode, EditorState},
+    },
 };
 
 /// Handles key events and updates the state of Habitui.
 pub fn handle_key_events(key_event: KeyEvent, app: &mut Habitui) -> Result<(), AppError> {
     // Exit application on `Ctrl-C`
-    if key_event.code == KeyCode::Char('c') || key_event.code == KeyCode::Char('C') {
-        if key_event.modifiers == KeyModifiers::CONTROL {
-            app.state = AppState::Exit;
+    if (key_event.code == KeyCode::Char('c') || key_event.code == KeyCode::Char('C'))
+        && key_event.modifiers == KeyModifiers::CONTROL
+    {
+        app.state = AppState::Exit;
 
-            return Ok(());
+        return Ok(());
+    }
+
+    if app.state == AppState::Diff {
+        match key_event.code {
+            KeyCode::Enter | KeyCode::Char('y') => app.handle_pending_edit(true),
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('n') => {
+                app.handle_pending_edit(false)
+            }
+            _ => {}
         }
+
+        return Ok(());
+    }
+
+    if app.state == AppState::TemplatePicker {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => app.state = AppState::List,
+            KeyCode::Enter => app.handle_pick_template(),
+            KeyCode::Char('j') | KeyCode::Down => app.template_picker.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => app.template_picker.select_previous(),
+            _ => {}
+        }
+
+        return Ok(());
+    }
+
+    if app.state == AppState::TagInput {
+        match key_event.code {
+            KeyCode::Esc => app.state = AppState::List,
+            KeyCode::Enter => app.handle_submit_tag(),
+            _ => {
+                app.tag_input.input(key_event);
+            }
+        }
+
+        return Ok(());
+    }
+
+    if app.state == AppState::Detail {
+        let detail = &mut app.detail_state;
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('z') => app.state = AppState::List,
+            KeyCode::Char(' ') => app.grid_state.mark_item_completed(),
+            KeyCode::Enter if !app.grid_state.modifications.is_empty() => {
+                app.handle_submit_modifications();
+            }
+            KeyCode::Char('j') | KeyCode::Down => match key_event.modifiers {
+                KeyModifiers::CONTROL => {
+                    app.grid_state.select_next_sub();
+                    detail.follow_selection();
+                }
+                _ => detail.scroll_down(1),
+            },
+            KeyCode::Char('k') | KeyCode::Up => match key_event.modifiers {
+                KeyModifiers::CONTROL => {
+                    app.grid_state.select_prev_sub();
+                    detail.follow_selection();
+                }
+                _ => detail.scroll_up(1),
+            },
+            KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+                detail.scroll_half_page_down()
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                detail.scroll_half_page_up()
+            }
+            KeyCode::Char('g') => detail.scroll_to_top(),
+            KeyCode::Char('G') => detail.scroll_to_bottom(),
+            _ => {}
+        }
+
+        return Ok(());
     }
 
     if app.state == AppState::Editor {
@@ -25,161 +101,21 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut Habitui) -> Result<(), A
 
         match editor.mode {
             EditorMode::Normal => match key_event.code {
-                KeyCode::Esc | KeyCode::Char('q') => {
+                KeyCode::Esc | KeyCode::Char('q') if !editor.vim.is_pending() => {
                     app.state = AppState::List;
                 }
-                KeyCode::Enter => {
-                    if editor.is_modified {
-                        let task = editor.clone_task();
+                KeyCode::Enter if editor.is_modified => {
+                    for task in editor.take_submission() {
                         app.handle_submit_task(task);
-
-                        app.state = AppState::List;
-                    }
-                }
-                KeyCode::Char('h') => editor.move_cursor(CursorMove::Back),
-                KeyCode::Char('l') => {
-                    if key_event.modifiers == KeyModifiers::CONTROL {
-                        editor.mode = EditorMode::Calendar;
-                    } else {
-                        editor.move_cursor(CursorMove::Forward);
                     }
-                }
-                KeyCode::Char('w') => {
-                    let mod_key = editor.pop_mod_key();
-                    if let Some((textarea, i)) = editor.get_focused_mut() {
-                        textarea.move_cursor(CursorMove::WordForward);
-                        match mod_key {
-                            Some(KeyEvent {
-                                code: _c @ KeyCode::Char('c'),
-                                ..
-                            }) => {
-                                textarea.delete_word();
-                                editor.mark_modified(i);
-                                editor.enter_insert_mode();
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                KeyCode::Char('b') => {
-                    let mod_key = editor.pop_mod_key();
-                    if let Some((textarea, i)) = editor.get_focused_mut() {
-                        match mod_key {
-                            Some(KeyEvent {
-                                code: _c @ KeyCode::Char('c'),
-                                ..
-                            }) => {
-                                textarea.delete_word();
-                                editor.mark_modified(i);
-                                editor.enter_insert_mode();
-                            }
-                            _ => textarea.move_cursor(CursorMove::WordBack),
-                        }
-                    }
-                }
-                KeyCode::Char('d') => {
-                    let mod_key = editor.pop_mod_key();
-                    match mod_key {
-                        Some(KeyEvent {
-                            code: _c @ KeyCode::Char('d'),
-                            ..
-                        }) => {
-                            if let Some((textarea, i)) = editor.get_focused_mut() {
-                                textarea.move_cursor(CursorMove::Head);
-                                textarea.delete_line_by_end();
-                                editor.mark_modified(i);
-                                editor.sync_changes();
-                            }
-                        }
-                        _ => {
-                            editor.add_mod_key(key_event);
-                        }
-                    }
-                }
-                KeyCode::Char('c') => {
-                    editor.add_mod_key(key_event);
-                }
-                KeyCode::Char('y') => {
-                    editor.add_mod_key(key_event);
-                }
-                KeyCode::Char('j') | KeyCode::Tab => {
-                    editor.focus = Some(editor.focus.map_or(0, |mut i| {
-                        i += 1;
-                        i.clamp(0, editor.fields.len() - 1)
-                    }));
-                }
-                KeyCode::Char('k') => {
-                    editor.focus = Some(editor.focus.map_or(0, |mut i| {
-                        i = i.saturating_sub(1);
-                        i.clamp(0, editor.fields.len() - 1)
-                    }));
-                }
-                KeyCode::Char('i') => {
-                    editor.enter_insert_mode();
-                }
-                KeyCode::Char('a') => {
-                    editor.move_cursor(CursorMove::Forward);
-                    editor.enter_insert_mode();
-                }
-                KeyCode::Char('o') | KeyCode::Char('O') => {
-                    editor.insert_subtask();
-                }
-                KeyCode::Char('+') => {
-                    editor.next_task_difficulty();
-                }
-                KeyCode::Char('-') => {
-                    editor.prev_task_difficulty();
-                }
-                _ => {}
-            },
-            EditorMode::Insert => match key_event.code {
-                KeyCode::Esc => editor.exit_insert_mode(),
-                KeyCode::Enter => match editor.focus {
-                    Some(i) if i < editor.fields.len() - 1 => {
-                        editor.focus = Some(i + 1);
-                    }
-                    Some(_) => {
-                        editor.insert_subtask();
-                    }
-                    None => {}
-                },
-                KeyCode::Tab => {
-                    let mut i = *editor.focus.get_or_insert(0);
-                    if key_event.modifiers == KeyModifiers::SHIFT {
-                        i = i.saturating_sub(1);
-                    } else {
-                        i += 1;
-                    }
-                    editor.focus = Some(i.clamp(0, editor.fields.len() - 1));
-                }
-                c => {
-                    if c == KeyCode::Char('j') {
-                        match editor.mod_key {
-                            Some((
-                                KeyEvent {
-                                    code: _c @ KeyCode::Char('j'),
-                                    ..
-                                },
-                                _,
-                            )) => editor.exit_insert_mode(),
-                            _ => editor.add_mod_key(key_event),
-                        }
-                        return Ok(());
-                    };
-                    let mod_key = editor.pop_mod_key();
 
-                    if let Some(index) = editor.focus {
-                        if let Some(textarea) = editor.fields.get_mut(index) {
-                            if let Some(pending) = mod_key {
-                                textarea.input(pending);
-                            }
-                            textarea.input(key_event);
-                            editor.dirty_fields.push(index);
-                            editor.is_modified = true;
-                        }
-                    }
+                    app.state = AppState::List;
                 }
+                _ => editor.handle_vim_key(key_event),
             },
+            EditorMode::Insert | EditorMode::Visual | EditorMode::VisualLine => {
+                editor.handle_vim_key(key_event)
+            }
             EditorMode::Calendar => match key_event.code {
                 KeyCode::Esc | KeyCode::Char('q') => {
                     app.state = AppState::List;
@@ -195,17 +131,17 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut Habitui) -> Result<(), A
                 KeyCode::Char('k') => editor.move_date_cursor(CursorMove::Up),
                 KeyCode::Char('l') => editor.move_date_cursor(CursorMove::Forward),
                 KeyCode::Char('x') => editor.remove_due_date(),
+                KeyCode::Char('i') | KeyCode::Char('/') => editor.enter_date_input(),
                 KeyCode::Char(' ') => {
                     editor.task.date = editor.date_focus;
                     editor.is_modified = true;
                 }
-                KeyCode::Enter => {
-                    if editor.is_modified {
-                        let task = editor.clone_task();
+                KeyCode::Enter if editor.is_modified => {
+                    for task in editor.take_submission() {
                         app.handle_submit_task(task);
-
-                        app.state = AppState::List;
                     }
+
+                    app.state = AppState::List;
                 }
                 KeyCode::Char('+') => {
                     editor.next_task_difficulty();
@@ -215,23 +151,54 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut Habitui) -> Result<(), A
                 }
                 _ => {}
             },
+            EditorMode::DateInput => match key_event.code {
+                KeyCode::Esc => editor.exit_date_input(),
+                KeyCode::Enter => editor.submit_date_input(),
+                _ => {
+                    editor.date_input.input(key_event);
+                    editor.date_input_error = false;
+                }
+            },
         }
 
         return Ok(());
     }
 
+    // Counts repeat motions, e.g. 3j
+    if let KeyCode::Char(c @ '0'..='9') = key_event.code {
+        if c != '0' || app.grid_state.count.is_some() {
+            app.grid_state.push_count_digit(c);
+            return Ok(());
+        }
+    }
+    let count = app.grid_state.count.take();
+    let repeat = count.unwrap_or(1).max(1);
+
     match key_event.code {
-        // Exit application on `ESC` or `q`
+        // Drop the picked tasks, or exit application on `ESC` or `q`
+        KeyCode::Esc | KeyCode::Char('q') if app.grid_state.has_multi_select() => {
+            app.grid_state.clear_multi_select()
+        }
         KeyCode::Esc | KeyCode::Char('q') => app.state = AppState::Exit,
 
+        // Pick tasks for bulk actions as a range or one by one
+        KeyCode::Char('V') => app.grid_state.toggle_visual(),
+        KeyCode::Char('m') => app.grid_state.toggle_marked(),
+
+        // Bulk actions, which apply to the selected task if none are picked
+        KeyCode::Char('+') => app.grid_state.change_difficulty(true),
+        KeyCode::Char('-') => app.grid_state.change_difficulty(false),
+        KeyCode::Char('>') => app.grid_state.shift_due_dates(repeat as i64),
+        KeyCode::Char('<') => app.grid_state.shift_due_dates(-(repeat as i64)),
+        KeyCode::Char('#') => app.open_tag_input(),
+        KeyCode::Char('T') => app.grid_state.move_to_top(),
+
         // Mark a task or subtask for completion
         KeyCode::Char(' ') => app.grid_state.mark_item_completed(),
 
         // Submit completed tasks or subtasks
-        KeyCode::Enter => {
-            if !app.grid_state.modifications.is_empty() {
-                app.handle_submit_modifications();
-            }
+        KeyCode::Enter if !app.grid_state.modifications.is_empty() => {
+            app.handle_submit_modifications();
         }
 
         // Enter editor to create new task or edit an existing one
@@ -239,6 +206,8 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut Habitui) -> Result<(), A
             app.state = AppState::Editor;
             app.editor_state = Some(EditorState::new(None));
         }
+        KeyCode::Char('t') => app.open_template_picker(),
+        KeyCode::Char('y') => app.handle_duplicate_task(),
         KeyCode::Char('e') => {
             let selected = app.grid_state.get_selected();
             if selected.is_some() {
@@ -246,53 +215,94 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut Habitui) -> Result<(), A
                 app.editor_state = Some(EditorState::new(selected));
             }
         }
+        // Zoom into the details of the selected task
+        KeyCode::Char('z') if app.grid_state.get_selected().is_some() => {
+            app.state = AppState::Detail;
+            app.detail_state = DetailState::default();
+            app.detail_state.follow_selection();
+        }
+        // Focus on the selected task with a pomodoro timer
+        KeyCode::Char('f') => app.handle_focus(),
+        KeyCode::Char('F') => app.handle_stop_focus(),
+        // Edit task in $VISUAL or $EDITOR
+        KeyCode::Char('E') => app.external_edit = app.grid_state.get_selected().cloned(),
         // Change selection with vim motions
         KeyCode::Char('h') => {
-            match key_event.modifiers {
-                KeyModifiers::ALT => app.grid_state.move_task(Direction::LEFT),
-                _ => app.grid_state.select_next(Direction::LEFT),
-            };
+            for _ in 0..repeat {
+                match key_event.modifiers {
+                    KeyModifiers::ALT => app.grid_state.move_task(Direction::LEFT),
+                    _ => app.grid_state.select_next(Direction::LEFT),
+                };
+            }
         }
         KeyCode::Char('j') => {
-            match key_event.modifiers {
-                KeyModifiers::ALT => app.grid_state.move_task(Direction::DOWN),
-                KeyModifiers::CONTROL => app.grid_state.select_next_sub(),
-                _ => app.grid_state.select_next(Direction::DOWN),
-            };
+            for _ in 0..repeat {
+                match key_event.modifiers {
+                    KeyModifiers::ALT => app.grid_state.move_task(Direction::DOWN),
+                    KeyModifiers::CONTROL => app.grid_state.select_next_sub(),
+                    _ => app.grid_state.select_next(Direction::DOWN),
+                };
+            }
         }
         KeyCode::Char('k') => {
-            match key_event.modifiers {
-                KeyModifiers::ALT => app.grid_state.move_task(Direction::UP),
-                KeyModifiers::CONTROL => app.grid_state.select_prev_sub(),
-                _ => app.grid_state.select_next(Direction::UP),
-            };
+            for _ in 0..repeat {
+                match key_event.modifiers {
+                    KeyModifiers::ALT => app.grid_state.move_task(Direction::UP),
+                    KeyModifiers::CONTROL => app.grid_state.select_prev_sub(),
+                    _ => app.grid_state.select_next(Direction::UP),
+                };
+            }
+        }
+        KeyCode::Char('l') => {
+            for _ in 0..repeat {
+                match key_event.modifiers {
+                    KeyModifiers::ALT => app.grid_state.move_task(Direction::RIGHT),
+                    _ => app.grid_state.select_next(Direction::RIGHT),
+                };
+            }
         }
-        KeyCode::Char('l') => match key_event.modifiers {
-            KeyModifiers::ALT => app.grid_state.move_task(Direction::RIGHT),
-            _ => app.grid_state.select_next(Direction::RIGHT),
-        },
 
         // Remove task
         KeyCode::Char('d') => app.grid_state.mark_item_removed(),
 
         // Change page
-        KeyCode::Char('J') => app.grid_state.next_page(),
-        KeyCode::Char('K') => app.grid_state.prev_page(),
+        KeyCode::Char('J') => (0..repeat).for_each(|_| app.grid_state.next_page()),
+        KeyCode::Char('K') => (0..repeat).for_each(|_| app.grid_state.prev_page()),
 
-        // Shift-g and gg motions
+        // Shift-g and gg motions, which go to the nth task with a count
         KeyCode::Char('g') => {
             if let Some(key) = app.grid_state.pop_mod_key() {
-                match key.code {
-                    KeyCode::Char('g') => {
-                        app.grid_state.select_first();
+                if let KeyCode::Char('g') = key.code {
+                    match count {
+                        Some(n) => app.grid_state.select_index(n.saturating_sub(1)),
+                        None => app.grid_state.select_first(),
                     }
-                    _ => {}
                 }
             } else {
+                app.grid_state.count = count;
                 app.grid_state.add_mod_key(key_event);
             }
         }
-        KeyCode::Char('G') => app.grid_state.select_last(),
+        KeyCode::Char('G') => match count {
+            Some(n) => app.grid_state.select_index(n.saturating_sub(1)),
+            None => app.grid_state.select_last(),
+        },
+
+        // Switch between the cards and the table, remembering the choice
+        KeyCode::Tab => {
+            app.grid_state.toggle_layout();
+            let session = Session {
+                layout: app.grid_state.layout,
+            };
+            if let Err(e) = save_session(&session) {
+                app.log_debug = Some((e.to_string(), 20));
+            }
+        }
+
+        // Switch between sorted views and commit the sorted order
+        KeyCode::Char('s') => app.grid_state.next_view(),
+        KeyCode::Char('S') => app.grid_state.toggle_grouped(),
+        KeyCode::Char('C') => app.handle_commit_view(),
 
         _ => {}
     }
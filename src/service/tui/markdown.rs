@@ -0,0 +1,358 @@
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::util::Palette;
+use crate::service::width::{display_width, grapheme_width};
+
+/// Emoji shortcodes supported in task texts, as in Habitica
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("alarm_clock", "⏰"),
+    ("apple", "🍎"),
+    ("bell", "🔔"),
+    ("book", "📖"),
+    ("books", "📚"),
+    ("bulb", "💡"),
+    ("calendar", "📆"),
+    ("car", "🚗"),
+    ("check", "✔️"),
+    ("clipboard", "📋"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("crossed_swords", "⚔️"),
+    ("dog", "🐶"),
+    ("dragon", "🐉"),
+    ("email", "📧"),
+    ("exclamation", "❗"),
+    ("fire", "🔥"),
+    ("gem", "💎"),
+    ("gift", "🎁"),
+    ("heart", "❤️"),
+    ("heavy_check_mark", "✔️"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("key", "🔑"),
+    ("lock", "🔒"),
+    ("memo", "📝"),
+    ("money_with_wings", "💸"),
+    ("moneybag", "💰"),
+    ("muscle", "💪"),
+    ("pencil", "📝"),
+    ("pencil2", "✏️"),
+    ("phone", "☎️"),
+    ("pill", "💊"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rocket", "🚀"),
+    ("running", "🏃"),
+    ("seedling", "🌱"),
+    ("shield", "🛡️"),
+    ("shopping_cart", "🛒"),
+    ("smile", "😄"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("sunny", "☀️"),
+    ("tada", "🎉"),
+    ("trophy", "🏆"),
+    ("warning", "⚠️"),
+    ("white_check_mark", "✅"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("zap", "⚡"),
+];
+
+pub fn emoji(shortcode: &str) -> Option<&'static str> {
+    EMOJI
+        .iter()
+        .find(|(code, _)| *code == shortcode)
+        .map(|(_, emoji)| *emoji)
+}
+
+fn code_style() -> Style {
+    Style::default().bg(Palette::BG.into())
+}
+
+fn push_str(spans: &mut Vec<Span<'static>>, s: &str, style: Style) {
+    match spans.last_mut() {
+        Some(span) if span.style == style => span.content.to_mut().push_str(s),
+        _ => spans.push(Span::styled(s.to_string(), style)),
+    }
+}
+
+fn push_char(spans: &mut Vec<Span<'static>>, c: char, style: Style) {
+    push_str(spans, c.encode_utf8(&mut [0; 4]), style);
+}
+
+/// Index of the first occurrence of pattern in chars at or after from
+fn find(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    (from..chars.len()).find(|&i| chars[i..].starts_with(&pattern))
+}
+
+/// Render inline Markdown (emphasis, code, links and emoji shortcodes) as
+/// styled spans
+pub fn render_inline(text: &str, style: Style) -> Vec<Span<'static>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let (mut bold, mut italic, mut strike) = (false, false, false);
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let mut current = style;
+        if bold {
+            current = current.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            current = current.add_modifier(Modifier::ITALIC);
+        }
+        if strike {
+            current = current.add_modifier(Modifier::CROSSED_OUT);
+        }
+
+        match c {
+            '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                push_char(&mut spans, chars[i + 1], current);
+                i += 2;
+                continue;
+            }
+            '`' => {
+                if let Some(end) = find(&chars, i + 1, "`") {
+                    let code: String = chars[i + 1..end].iter().collect();
+                    push_str(&mut spans, &code, current.patch(code_style()));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            '*' | '_' | '~' => {
+                let double = chars.get(i + 1) == Some(&c);
+                let flag = match (c, double) {
+                    ('~', true) => Some(&mut strike),
+                    ('~', false) => None,
+                    (_, true) => Some(&mut bold),
+                    (_, false) => Some(&mut italic),
+                };
+                if let Some(flag) = flag {
+                    let len = if double { 2 } else { 1 };
+                    let delimiter: String = chars[i..i + len].iter().collect();
+                    // Underscores within words, e.g. snake_case, are not
+                    // emphasis
+                    let in_word = c == '_' && i > 0 && chars[i - 1].is_alphanumeric();
+                    let opens = !in_word
+                        && chars.get(i + len).is_some_and(|n| !n.is_whitespace())
+                        && find(&chars, i + len + 1, &delimiter).is_some();
+                    if *flag || opens {
+                        *flag = !*flag;
+                        i += len;
+                        continue;
+                    }
+                }
+            }
+            '[' => {
+                let link = find(&chars, i + 1, "](")
+                    .and_then(|mid| find(&chars, mid + 2, ")").map(|end| (mid, end)));
+                if let Some((mid, end)) = link {
+                    let label: String = chars[i + 1..mid].iter().collect();
+                    let label_style = current.add_modifier(Modifier::UNDERLINED);
+                    for span in render_inline(&label, label_style) {
+                        push_str(&mut spans, &span.content, span.style);
+                    }
+                    i = end + 1;
+                    continue;
+                }
+            }
+            ':' => {
+                let shortcode = find(&chars, i + 1, ":").and_then(|end| {
+                    let code: String = chars[i + 1..end].iter().collect();
+                    emoji(&code).map(|e| (e, end))
+                });
+                if let Some((emoji, end)) = shortcode {
+                    push_str(&mut spans, emoji, current);
+                    i = end + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        push_char(&mut spans, c, current);
+        i += 1;
+    }
+
+    spans
+}
+
+/// Render a line of Markdown along with the indentation its continuation
+/// lines should have when wrapped
+fn render_block(text: &str) -> (Line<'static>, usize) {
+    let trimmed = text.trim_start();
+    let lead = &text[..text.len() - trimmed.len()];
+    let mut spans = vec![Span::raw(lead.to_string())];
+
+    let heading_level = trimmed.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&heading_level) && trimmed[heading_level..].starts_with(' ') {
+        let heading = trimmed[heading_level..].trim();
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        spans.extend(render_inline(heading, style));
+        return (Line::from(spans), 0);
+    }
+
+    let (prefix, rest, style) = if let Some(rest) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| trimmed.strip_prefix(bullet))
+    {
+        ("• ".to_string(), rest, Style::default())
+    } else if let Some(rest) = trimmed.strip_prefix("> ") {
+        (
+            "│ ".to_string(),
+            rest,
+            Style::default().add_modifier(Modifier::ITALIC),
+        )
+    } else {
+        let n_digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+        match trimmed[n_digits..].strip_prefix(". ") {
+            Some(rest) if n_digits > 0 => (
+                format!("{}. ", &trimmed[..n_digits]),
+                rest,
+                Style::default(),
+            ),
+            _ => (String::new(), trimmed, Style::default()),
+        }
+    };
+
+    let indent = if prefix.is_empty() {
+        0
+    } else {
+        display_width(lead) + display_width(&prefix)
+    };
+    spans.push(Span::raw(prefix));
+    spans.extend(render_inline(rest, style));
+
+    (Line::from(spans), indent)
+}
+
+/// Render Markdown text as lines of styled spans wrapped to the given width
+pub fn render_markdown(text: &str, width: usize) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+    for text_line in text.lines() {
+        if text_line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        let (line, indent) = if in_code_block {
+            (Line::styled(text_line.to_string(), code_style()), 0)
+        } else {
+            render_block(text_line)
+        };
+        lines.extend(wrap_line(line, width, indent));
+    }
+
+    lines
+}
+
+/// Width of a line in terminal columns
+pub fn line_width(line: &Line) -> usize {
+    line.spans.iter().map(|s| display_width(&s.content)).sum()
+}
+
+/// Graphemes of a line along with their styles
+fn graphemes<'a>(line: &'a Line) -> Vec<(&'a str, Style)> {
+    line.spans
+        .iter()
+        .flat_map(|s| s.content.graphemes(true).map(move |g| (g, s.style)))
+        .collect()
+}
+
+/// Shorten a line to fit in the given width, marking the cut with an ellipsis
+pub fn truncate_line(line: Line<'static>, width: usize) -> Line<'static> {
+    if line_width(&line) <= width {
+        return line;
+    }
+
+    let max = width.saturating_sub(1);
+    let mut spans = Vec::new();
+    let mut truncated_width = 0;
+    for (g, style) in graphemes(&line) {
+        let w = grapheme_width(g);
+        if truncated_width + w > max {
+            break;
+        }
+        push_str(&mut spans, g, style);
+        truncated_width += w;
+    }
+    let style = spans.last().map_or(Style::default(), |s| s.style);
+    push_str(&mut spans, "…", style);
+
+    let mut truncated = Line::from(spans).style(line.style);
+    truncated.alignment = line.alignment;
+    truncated
+}
+
+/// Wrap a line at word boundaries so that each line fits in the given width.
+/// Words longer than the width are broken, and the lines after the first
+/// one are indented by the given amount
+pub fn wrap_line(line: Line<'static>, width: usize, indent: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let indent = if indent < width { indent } else { 0 };
+    let graphemes = graphemes(&line);
+    let is_space = |j: &usize| graphemes[*j].0.chars().all(char::is_whitespace);
+
+    let new_line = |spans| {
+        let mut l = Line::from(spans).style(line.style);
+        l.alignment = line.alignment;
+        l
+    };
+
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut line_width = 0;
+    let mut line_has_words = false;
+    let mut break_line = |spans: &mut Vec<Span<'static>>, line_width: &mut usize| {
+        lines.push(new_line(std::mem::take(spans)));
+        spans.push(Span::raw(" ".repeat(indent)));
+        *line_width = indent;
+    };
+
+    let mut i = 0;
+    while i < graphemes.len() {
+        let word_start = (i..graphemes.len())
+            .find(|j| !is_space(j))
+            .unwrap_or(graphemes.len());
+        let word_end = (word_start..graphemes.len())
+            .find(is_space)
+            .unwrap_or(graphemes.len());
+        let width_of = |gs: &[(&str, Style)]| gs.iter().map(|(g, _)| grapheme_width(g)).sum();
+        let space_width: usize = width_of(&graphemes[i..word_start]);
+        let word_width: usize = width_of(&graphemes[word_start..word_end]);
+
+        // Spaces at the point where a line is broken are dropped
+        if line_has_words && line_width + space_width + word_width > width {
+            break_line(&mut spans, &mut line_width);
+            line_has_words = false;
+        } else {
+            for &(g, style) in &graphemes[i..word_start] {
+                push_str(&mut spans, g, style);
+            }
+            line_width += space_width;
+        }
+
+        for &(g, style) in &graphemes[word_start..word_end] {
+            let w = grapheme_width(g);
+            if line_has_words && line_width + w > width {
+                break_line(&mut spans, &mut line_width);
+            }
+            push_str(&mut spans, g, style);
+            line_width += w;
+            line_has_words = true;
+        }
+        i = word_end;
+    }
+    lines.push(new_line(spans));
+
+    lines
+}
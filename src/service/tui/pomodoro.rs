@@ -0,0 +1,237 @@
+use std::fmt;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use sqlx::{types::uuid::Uuid, PgPool};
+use time::OffsetDateTime;
+use tokio::sync::OnceCell;
+
+use crate::{
+    error::AppError,
+    service::{config::get_config, util::create_pg_pool},
+};
+
+// Connection pool shared by the sessions logged while the TUI runs
+static POOL: OnceCell<PgPool> = OnceCell::const_new();
+
+/// Lengths of the focus sessions and breaks of the pomodoro timer in minutes
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroConfig {
+    pub work: u64,
+    pub short_break: u64,
+    pub long_break: u64,
+    /// Number of focus sessions after which the break is a long one
+    pub long_break_after: u32,
+    /// Mark the checklist item selected when a focus session started, or the
+    /// task if none was, as completed when the session ends
+    pub mark_completed: bool,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work: 25,
+            short_break: 5,
+            long_break: 15,
+            long_break_after: 4,
+            mark_completed: false,
+        }
+    }
+}
+
+impl PomodoroConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.work == 0 {
+            return Err("pomodoro work length must be at least a minute".into());
+        }
+        if self.long_break_after == 0 {
+            return Err("pomodoro long_break_after must be at least 1".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Phase {
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Focus => write!(f, "Focus"),
+            Phase::ShortBreak => write!(f, "Break"),
+            Phase::LongBreak => write!(f, "Long break"),
+        }
+    }
+}
+
+fn minutes(n: u64) -> Duration {
+    Duration::from_secs(n * 60)
+}
+
+pub struct Timer {
+    pub phase: Phase,
+    length: Duration,
+    ends_at: Instant,
+    /// Time left on the timer while it is paused
+    paused_left: Option<Duration>,
+    started_at: OffsetDateTime,
+}
+
+impl Timer {
+    fn new(phase: Phase, length: Duration) -> Self {
+        Self {
+            phase,
+            length,
+            ends_at: Instant::now() + length,
+            paused_left: None,
+            started_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.paused_left
+            .unwrap_or_else(|| self.ends_at.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_left.is_some()
+    }
+
+    fn toggle_pause(&mut self) {
+        match self.paused_left.take() {
+            Some(left) => self.ends_at = Instant::now() + left,
+            None => self.paused_left = Some(self.remaining()),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        !self.is_paused() && self.remaining().is_zero()
+    }
+}
+
+/// Task a focus session was started on
+#[derive(Clone)]
+pub struct FocusTask {
+    pub id: Uuid,
+    pub text: String,
+    /// Text of the checklist item selected at the start
+    pub subtask: Option<String>,
+}
+
+/// Focus session which has ended, to be logged in the database
+pub struct FocusSession {
+    pub task: FocusTask,
+    started_at: OffsetDateTime,
+    ended_at: OffsetDateTime,
+    planned: Duration,
+    focused: Duration,
+    completed: bool,
+}
+
+impl FocusSession {
+    fn new(timer: &Timer, task: FocusTask, completed: bool) -> Self {
+        Self {
+            task,
+            started_at: timer.started_at,
+            ended_at: OffsetDateTime::now_utc(),
+            planned: timer.length,
+            focused: timer.length.saturating_sub(timer.remaining()),
+            completed,
+        }
+    }
+}
+
+/// Pomodoro timer, which alternates focus sessions on a task with breaks
+#[derive(Default)]
+pub struct PomodoroState {
+    pub timer: Option<Timer>,
+    pub task: Option<FocusTask>,
+    /// Focus sessions completed since the TUI was started
+    n_completed: u32,
+}
+
+impl PomodoroState {
+    pub fn start_focus(&mut self, task: FocusTask) {
+        self.task = Some(task);
+        self.timer = Some(Timer::new(
+            Phase::Focus,
+            minutes(get_config().pomodoro.work),
+        ));
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if let Some(timer) = self.timer.as_mut() {
+            timer.toggle_pause();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.timer.is_some()
+    }
+
+    /// Stop the timer. Returns the focus session if one was cut short
+    pub fn stop(&mut self) -> Option<FocusSession> {
+        let timer = self.timer.take()?;
+        let task = self.task.take()?;
+        (timer.phase == Phase::Focus).then(|| FocusSession::new(&timer, task, false))
+    }
+
+    /// Start a break once a focus session runs out, and stop the timer once
+    /// the break does. Returns the focus session which ran out
+    pub fn tick(&mut self) -> Option<FocusSession> {
+        let timer = self.timer.as_ref().filter(|t| t.is_done())?;
+        if timer.phase != Phase::Focus {
+            self.timer = None;
+            self.task = None;
+            return None;
+        }
+
+        let config = &get_config().pomodoro;
+        self.n_completed += 1;
+        let next = if self.n_completed.is_multiple_of(config.long_break_after) {
+            Timer::new(Phase::LongBreak, minutes(config.long_break))
+        } else {
+            Timer::new(Phase::ShortBreak, minutes(config.short_break))
+        };
+        let focus = mem::replace(self.timer.as_mut()?, next);
+        let task = self.task.clone()?;
+
+        Some(FocusSession::new(&focus, task, true))
+    }
+}
+
+pub async fn log_session(session: FocusSession) -> Result<(), AppError> {
+    let pool = POOL.get_or_try_init(create_pg_pool).await?;
+    sqlx::query(
+        "
+        INSERT INTO pomodoro_session (
+            task_id,
+            task_text,
+            subtask,
+            started_at,
+            ended_at,
+            planned_secs,
+            focused_secs,
+            completed
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ",
+    )
+    .bind(session.task.id)
+    .bind(session.task.text)
+    .bind(session.task.subtask)
+    .bind(session.started_at)
+    .bind(session.ended_at)
+    .bind(session.planned.as_secs() as i32)
+    .bind(session.focused.as_secs() as i32)
+    .bind(session.completed)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use super::widgets::grid::GridLayout;
+use crate::{error::AppError, util::build_config_path};
+
+/// Choices made in the TUI which are remembered across sessions
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub layout: GridLayout,
+}
+
+fn get_session_path() -> Result<String, AppError> {
+    let dir = build_config_path()?;
+    Ok(format!("{dir}/tui_session.json"))
+}
+
+/// The session saved last time, or the defaults if there is none
+pub fn load_session() -> Session {
+    get_session_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_session(session: &Session) -> Result<(), AppError> {
+    fs::write(get_session_path()?, serde_json::to_string(session)?)?;
+    Ok(())
+}
@@ -37,8 +37,7 @@ impl Tui {
     ///
     /// It enables the raw mode and sets terminal properties.
     pub fn init(&mut self) -> Result<(), AppError> {
-        terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        self.enter()?;
 
         // Define a custom panic hook to reset the terminal properties.
         // This way, you won't have your terminal messed up if an unexpected error happens.
@@ -48,11 +47,33 @@ impl Tui {
             panic_hook(panic);
         }));
 
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Result<(), AppError> {
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
         self.terminal.hide_cursor()?;
         self.terminal.clear()?;
         Ok(())
     }
 
+    /// Suspends the terminal interface to hand the terminal over to another
+    /// program, such as a text editor.
+    pub async fn suspend(&mut self) -> Result<(), AppError> {
+        self.events.stop().await;
+        self.exit()
+    }
+
+    /// Resumes the terminal interface after [`suspend`].
+    ///
+    /// [`suspend`]: Tui::suspend
+    pub fn resume(&mut self) -> Result<(), AppError> {
+        self.enter()?;
+        self.events.restart();
+        Ok(())
+    }
+
     /// [`Draw`] the terminal interface by [`rendering`] the widgets.
     ///
     /// [`Draw`]: ratatui::Terminal::draw
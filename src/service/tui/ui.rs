@@ -2,15 +2,27 @@ use std::env;
 
 use ratatui::{
     layout::{Constraint, Layout, Rect},
-    style::Style,
-    widgets::{Block, BorderType, Clear, Padding, Paragraph},
+    style::{Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, BorderType, Clear, List, Padding, Paragraph},
     Frame,
 };
 
+use crate::service::{
+    config::get_config,
+    document::{diff_tasks, DiffLine},
+};
+
 use super::{
     app::{AppState, Habitui},
+    markdown::{render_inline, truncate_line},
+    pomodoro::Phase,
     util::Palette,
-    widgets::{editor::Editor, grid::TaskGrid},
+    widgets::{
+        detail::TaskDetail,
+        editor::Editor,
+        grid::{GridView, TaskGrid},
+    },
 };
 
 const TITLE_STR: &str = "╻ ╻┏━┓┏┓ ╻╺┳╸╻ ╻╻\n┣━┫┣━┫┣┻┓┃ ┃ ┃ ┃┃\n╹ ╹╹ ╹┗━┛╹ ╹ ┗━┛╹";
@@ -21,28 +33,143 @@ fn render_task_grid(f: &mut Frame, area: Rect, app: &mut Habitui) {
     f.render_stateful_widget(widget, area, state);
 }
 
-fn render_footer(f: &mut Frame, area: Rect) {
+/// Countdown of the pomodoro timer along with the task it was started on
+fn render_pomodoro(f: &mut Frame, area: Rect, app: &Habitui) {
+    let pomodoro = &app.pomodoro;
+    let (Some(timer), Some(task)) = (&pomodoro.timer, &pomodoro.task) else {
+        return;
+    };
+    let width = area.width.min(40);
+    let timer_area = Rect {
+        x: area.x + area.width - width,
+        width,
+        ..area
+    };
+    let text_width = width.saturating_sub(2) as usize;
+
+    let secs = timer.remaining().as_millis().div_ceil(1000);
+    let (icon, color) = match timer.phase {
+        _ if timer.is_paused() => ("⏸", Palette::YELLOW),
+        Phase::Focus => ("🍅", Palette::RED),
+        Phase::ShortBreak | Phase::LongBreak => ("☕", Palette::GREEN),
+    };
+    let countdown = format!(" {icon} {} {:02}:{:02} ", timer.phase, secs / 60, secs % 60);
+    let mut lines = vec![
+        Line::styled(
+            countdown,
+            Style::default()
+                .bg(color.into())
+                .add_modifier(Modifier::BOLD),
+        ),
+        truncate_line(
+            Line::from(render_inline(&task.text, Style::default())),
+            text_width,
+        ),
+    ];
+    if let Some(subtask) = &task.subtask {
+        let mut spans = vec!["└ ".into()];
+        spans.extend(render_inline(subtask, Style::default()));
+        lines.push(truncate_line(Line::from(spans), text_width));
+    }
+
+    f.render_widget(
+        Paragraph::new(lines)
+            .right_aligned()
+            .block(Block::default().padding(Padding::right(2))),
+        timer_area,
+    );
+}
+
+fn render_footer(f: &mut Frame, area: Rect, app: &Habitui) {
     let style = Style::default().fg(Palette::BG2.into());
-    let block = Block::bordered()
+    let mut block = Block::bordered()
         .border_style(style)
         .border_type(BorderType::Rounded)
         .padding(Padding::horizontal(2));
 
-    f.render_widget(
-        Paragraph::new(
-            "\
+    let grid_state = &app.grid_state;
+    if grid_state.view != GridView::Server {
+        let grouped = if grid_state.grouped { ", grouped" } else { "" };
+        block = block.title(
+            Line::from(format!(" sorted by {}{grouped} ", grid_state.view))
+                .fg(Palette::FG)
+                .right_aligned(),
+        );
+    }
+
+    let help = if app.state == AppState::TemplatePicker {
+        "\
+      j/k: select template | \
+      enter: create task | \
+      esc: cancel\
+    "
+    } else if app.state == AppState::TagInput {
+        "\
+      enter: add tag | \
+      esc: cancel\
+    "
+    } else if app.state == AppState::List && grid_state.has_multi_select() {
+        "\
+      esc: drop picked | \
+      V/m: pick range/task | \
+      space: mark completed | \
+      d: mark for deletion | \
+      +/-: difficulty | \
+      </>: shift due date | \
+      #: add tag | \
+      T: move to top | \
+      enter: submit edit\
+    "
+    } else if app.state == AppState::Detail {
+        "\
+      q/z: close | \
+      j/k: scroll | \
+      ctrl-d/u: half page | \
+      ctrl-j/k: select subtask | \
+      space: mark completed | \
+      enter: submit\
+    "
+    } else if app.state == AppState::Diff {
+        "\
+      enter/y: submit changes | \
+      esc/n: discard changes\
+    "
+    } else if app.state == AppState::Editor {
+        "\
+      q: close | \
+      i: insert | \
+      v/V: visual | \
+      u/ctrl-r: undo/redo | \
+      .: repeat | \
+      o: add subtask | \
+      space: toggle subtask | \
+      alt-j/k: move subtask | \
+      D: delete subtask | \
+      T: subtask to TODO | \
+      enter: submit\
+    "
+    } else {
+        "\
       q: quit | \
       hjkl: navigate | \
       a: create task | \
+      t: from template | \
+      y: duplicate | \
       e: edit task | \
+      z: details | \
+      f/F: focus/stop | \
+      E: edit in $EDITOR | \
       space: mark completed | \
       d: mark for deletion | \
+      V/m: pick tasks | \
+      s/S: sort/group view | \
+      tab: cards/table | \
+      C: commit view order | \
       enter: submit edit\
-    ",
-        )
-        .block(block),
-        area,
-    );
+    "
+    };
+
+    f.render_widget(Paragraph::new(help).block(block), area);
 }
 
 fn calculate_editor_area(base_area: Rect) -> Rect {
@@ -72,6 +199,87 @@ fn render_editor(f: &mut Frame, area: Rect, app: &mut Habitui) {
     }
 }
 
+fn render_diff(f: &mut Frame, area: Rect, app: &Habitui) {
+    let Some((original, edited)) = &app.pending_edit else {
+        return;
+    };
+    let popup_area = calculate_editor_area(area);
+
+    let lines: Vec<Line> = diff_tasks(original, edited)
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Same(l) => Line::from(format!("  {l}")),
+            DiffLine::Added(l) => Line::from(format!("+ {l}")).bg(Palette::GREEN),
+            DiffLine::Removed(l) => Line::from(format!("- {l}")).bg(Palette::RED),
+        })
+        .collect();
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::bordered()
+                .title(" Submit changes? ")
+                .style(Style::default().bg(Palette::BG.into()))
+                .border_style(Style::default().fg(Palette::BG2.into()))
+                .padding(Padding::proportional(1)),
+        ),
+        popup_area,
+    );
+}
+
+fn render_detail(f: &mut Frame, area: Rect, app: &mut Habitui) {
+    let grid_state = &app.grid_state;
+    let Some(task) = grid_state.get_selected() else {
+        return;
+    };
+    let (task, completed) = grid_state.with_modifications(task);
+    let detail = TaskDetail::new(task, completed, grid_state.selected_sub);
+
+    f.render_stateful_widget(detail, area, &mut app.detail_state);
+}
+
+fn render_tag_input(f: &mut Frame, area: Rect, app: &mut Habitui) {
+    let popup_area = Rect {
+        x: area.x + area.width.saturating_sub(40) / 2,
+        y: area.y + area.height.saturating_sub(3) / 2,
+        width: area.width.min(40),
+        height: 3,
+    };
+    let n_targets = app.grid_state.get_targets().len();
+    let plural = if n_targets == 1 { "" } else { "s" };
+    app.tag_input.set_block(
+        Block::bordered()
+            .title(format!(" Add tag to {n_targets} task{plural} "))
+            .style(Style::default().bg(Palette::BG.into()))
+            .border_style(Style::default().fg(Palette::BG2.into())),
+    );
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(&app.tag_input, popup_area);
+}
+
+fn render_template_picker(f: &mut Frame, area: Rect, app: &mut Habitui) {
+    let names: Vec<&str> = get_config().templates.names().collect();
+    let height = (names.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + area.width.saturating_sub(40) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width: area.width.min(40),
+        height,
+    };
+    let list = List::new(names)
+        .block(
+            Block::bordered()
+                .title(" New task from template ")
+                .style(Style::default().bg(Palette::BG.into()))
+                .border_style(Style::default().fg(Palette::BG2.into())),
+        )
+        .highlight_style(Style::default().bg(Palette::GREEN.into()));
+
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(list, popup_area, &mut app.template_picker);
+}
+
 fn render_debug(f: &mut Frame, area: Rect, msg: &String) {
     let popup_area = Rect {
         x: (area.width / 8) * 6,
@@ -79,7 +287,7 @@ fn render_debug(f: &mut Frame, area: Rect, msg: &String) {
         width: area.width / 6,
         height: area.height / 6,
     };
-    f.render_widget(Clear::default(), popup_area);
+    f.render_widget(Clear, popup_area);
     f.render_widget(
         Paragraph::new(msg.to_string()).block(Block::bordered().padding(Padding::proportional(1))),
         popup_area,
@@ -100,18 +308,33 @@ pub fn render(frame: &mut Frame, app: &mut Habitui) {
         title_area,
     );
 
+    render_pomodoro(frame, title_area, app);
+
     render_task_grid(frame, main_area, app);
 
-    match app.state {
-        AppState::Editor => {
-            render_editor(frame, main_area, app);
-        }
-        _ => {}
+    if app.state == AppState::Editor {
+        render_editor(frame, main_area, app);
+    }
+
+    if app.state == AppState::Detail {
+        render_detail(frame, main_area, app);
+    }
+
+    if app.state == AppState::TemplatePicker {
+        render_template_picker(frame, main_area, app);
+    }
+
+    if app.state == AppState::TagInput {
+        render_tag_input(frame, main_area, app);
+    }
+
+    if app.state == AppState::Diff {
+        render_diff(frame, main_area, app);
     }
 
-    render_footer(frame, footer_area);
+    render_footer(frame, footer_area, app);
 
-    if let Ok(_) = env::var("HUTCTL_DEBUG") {
+    if env::var("HUTCTL_DEBUG").is_ok() {
         if let Some((msg, _)) = &app.log_debug {
             render_debug(frame, main_area, msg);
         }
@@ -1,5 +1,8 @@
 use ratatui::style::Color;
 
+use crate::service::{config::get_config, types::Priority};
+
+#[allow(clippy::upper_case_acronyms)]
 pub enum Direction {
     UP,
     DOWN,
@@ -8,6 +11,7 @@ pub enum Direction {
 }
 
 #[cfg(not(feature = "dark-mode"))]
+#[allow(clippy::upper_case_acronyms)]
 #[repr(u32)]
 pub enum Palette {
     FG = 0x005c6166,      // #5c6166
@@ -22,6 +26,7 @@ pub enum Palette {
 }
 
 #[cfg(feature = "dark-mode")]
+#[allow(clippy::upper_case_acronyms)]
 #[repr(u32)]
 pub enum Palette {
     FG = 0x005c6166,    // #5c6166
@@ -35,10 +40,37 @@ pub enum Palette {
     CURSOR = 0x00254428, // #254428
 }
 
-impl Into<Color> for Palette {
-    fn into(self) -> Color {
-        Color::from_u32(self as u32)
+impl From<Palette> for Color {
+    fn from(val: Palette) -> Self {
+        Color::from_u32(val as u32)
     }
 }
 
 pub const MOD_KEY_TTL: u32 = 50;
+
+/// Colour marking a task with the given priority. Tasks with the default
+/// priority are not marked, the rest fade from yellow to orange as the
+/// priority rises
+pub fn priority_color(priority: Priority) -> Option<Color> {
+    let priority_config = &get_config().priority;
+    let default = priority_config.default_priority();
+    if priority <= default {
+        return None;
+    }
+
+    let n_levels = (priority_config.highest().0 - default.0) as u32;
+    let rank = (priority.0 - default.0) as u32;
+    let (low, high) = (Palette::YELLOW2 as u32, Palette::CURSOR as u32);
+
+    let channel = |shift: u32| {
+        let (l, h) = ((low >> shift) & 0xff, (high >> shift) & 0xff);
+        let c = if n_levels <= 1 {
+            h
+        } else {
+            (l * (n_levels - rank) + h * (rank - 1)) / (n_levels - 1)
+        };
+        c << shift
+    };
+
+    Some(Color::from_u32(channel(16) | channel(8) | channel(0)))
+}
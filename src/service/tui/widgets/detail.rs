@@ -0,0 +1,224 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Clear, Padding, Paragraph, StatefulWidget, Widget},
+};
+use time::{format_description::well_known::Iso8601, macros::format_description, OffsetDateTime};
+
+use crate::service::{
+    date::{countdown, local_today, to_local},
+    tui::{
+        markdown::{render_inline, render_markdown, wrap_line},
+        util::Palette,
+    },
+    types::{SubTask, Task},
+    util::get_tag_names,
+    width::display_width,
+};
+
+// Width of the labels of the fields
+const LABEL_WIDTH: usize = 12;
+
+#[derive(Default)]
+pub struct DetailState {
+    pub scroll: usize,
+    // Scroll to the selected subtask on the next render
+    follow_selection: bool,
+    max_scroll: usize,
+    page_height: usize,
+}
+
+impl DetailState {
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll = (self.scroll + n).min(self.max_scroll);
+    }
+
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll = self.scroll.saturating_sub(n);
+    }
+
+    pub fn scroll_half_page_down(&mut self) {
+        self.scroll_down((self.page_height / 2).max(1));
+    }
+
+    pub fn scroll_half_page_up(&mut self) {
+        self.scroll_up((self.page_height / 2).max(1));
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = 0;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll = self.max_scroll;
+    }
+
+    pub fn follow_selection(&mut self) {
+        self.follow_selection = true;
+    }
+}
+
+/// Full screen view of every field of a task
+pub struct TaskDetail<'a> {
+    task: &'a Task,
+    completed: bool,
+    selected_sub: Option<usize>,
+}
+
+impl<'a> TaskDetail<'a> {
+    pub fn new(task: &'a Task, completed: bool, selected_sub: Option<usize>) -> Self {
+        Self {
+            task,
+            completed,
+            selected_sub,
+        }
+    }
+}
+
+fn format_time(datetime: Option<OffsetDateTime>) -> String {
+    datetime
+        .and_then(|dt| {
+            to_local(dt)
+                .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+                .ok()
+        })
+        .unwrap_or("-".into())
+}
+
+fn field_line(label: &str, value: Vec<Span<'static>>) -> Line<'static> {
+    let mut spans = vec![Span::styled(
+        format!("{label:LABEL_WIDTH$}"),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    spans.extend(value);
+    Line::from(spans)
+}
+
+impl TaskDetail<'_> {
+    /// Lines of the view wrapped to the given width, along with the index of
+    /// the line where each subtask starts
+    fn lines(&self, width: usize) -> (Vec<Line<'static>>, Vec<usize>) {
+        let task = self.task;
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+
+        let mut title = render_inline(&task.text, bold);
+        if self.completed {
+            title.push(Span::raw(" ✅"));
+        }
+        let mut lines = wrap_line(Line::from(title), width, 0);
+        lines.push(Line::default());
+
+        let due = match task.date {
+            Some(date) => {
+                let day = date.format(&Iso8601::DATE).unwrap_or_default();
+                let countdown = countdown(date);
+                let is_overdue = date.date() < local_today();
+                let countdown_style = if is_overdue {
+                    Style::default().bg(Palette::RED.into())
+                } else {
+                    Style::default()
+                };
+                vec![
+                    Span::raw(format!("{day} ")),
+                    Span::styled(format!("({countdown})"), countdown_style),
+                ]
+            }
+            None => vec![Span::raw("-")],
+        };
+        let tags = get_tag_names(&task.tags);
+        let tags = if tags.is_empty() {
+            "-".into()
+        } else {
+            tags.join(", ")
+        };
+        let fields = [
+            ("Difficulty", vec![Span::raw(task.difficulty.to_string())]),
+            ("Due", due),
+            ("Tags", vec![Span::raw(tags)]),
+            ("Created", vec![Span::raw(format_time(task.created_at))]),
+            ("Updated", vec![Span::raw(format_time(task.updated_at))]),
+        ];
+        for (label, value) in fields {
+            lines.extend(wrap_line(field_line(label, value), width, LABEL_WIDTH));
+        }
+
+        if let Some(notes) = task.notes.as_ref().filter(|n| !n.is_empty()) {
+            lines.push(Line::default());
+            lines.push(Line::styled("Notes", bold));
+            lines.extend(render_markdown(notes, width));
+        }
+
+        let mut subtask_lines = Vec::new();
+        if let Some(checklist) = task.checklist.as_ref().filter(|c| !c.is_empty()) {
+            let n_done = checklist.iter().filter(|s| s.completed).count();
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                format!("Checklist {n_done}/{}", checklist.len()),
+                bold,
+            ));
+            for (i, SubTask { text, completed }) in checklist.iter().enumerate() {
+                let check = match (completed, cfg!(feature = "dark-mode")) {
+                    (true, _) => "✅",
+                    (false, true) => "⬛",
+                    (false, false) => "⬜",
+                };
+                let style = match self.selected_sub {
+                    Some(s) if s == i => Style::default().bg(Palette::GREEN2.into()),
+                    _ => Style::default(),
+                };
+                let mut spans = vec![Span::raw(format!("{check} "))];
+                spans.extend(render_inline(text, Style::default()));
+
+                subtask_lines.push(lines.len());
+                let indent = display_width(check) + 1;
+                let wrapped = wrap_line(Line::from(spans), width, indent);
+                lines.extend(wrapped.into_iter().map(|l| l.style(style)));
+            }
+        }
+
+        (lines, subtask_lines)
+    }
+}
+
+impl StatefulWidget for TaskDetail<'_> {
+    type State = DetailState;
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut DetailState) {
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Palette::BG2.into()))
+            .style(Style::default().bg(Palette::BG.into()))
+            .padding(Padding::proportional(1));
+        let inner = block.inner(area);
+        let height = inner.height as usize;
+
+        let (lines, subtask_lines) = self.lines(inner.width as usize);
+        state.page_height = height;
+        state.max_scroll = lines.len().saturating_sub(height);
+
+        if state.follow_selection {
+            state.follow_selection = false;
+            if let Some(&start) = self.selected_sub.and_then(|i| subtask_lines.get(i)) {
+                if start < state.scroll {
+                    state.scroll = start;
+                } else if start >= state.scroll + height {
+                    state.scroll = start + 1 - height;
+                }
+            }
+        }
+        state.scroll = state.scroll.min(state.max_scroll);
+
+        let mut block = block;
+        if state.max_scroll > 0 {
+            let position = format!(" {}/{} ", state.scroll + height, lines.len());
+            block = block.title_bottom(Line::from(position).right_aligned().fg(Palette::FG));
+        }
+
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .scroll((state.scroll as u16, 0))
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -2,21 +2,27 @@ use crossterm::event::KeyEvent;
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
     widgets::{
         calendar::{CalendarEventStore, Monthly},
         BorderType, Paragraph,
     },
     widgets::{Block, Borders, Clear, Padding, StatefulWidget, Widget},
 };
-use std::cmp::max;
 use std::marker::PhantomData;
 use time::{Duration, OffsetDateTime};
 use tui_textarea::{CursorMove, TextArea};
 
+mod vim;
+
+use vim::VimState;
+
 use crate::service::{
-    tui::util::{Palette, MOD_KEY_TTL},
+    date::local_today,
+    tui::util::{Direction, Palette, MOD_KEY_TTL},
     types::{Difficulty, SubTask, Task},
+    util::parse_due_date,
 };
 
 #[derive(PartialEq)]
@@ -24,6 +30,9 @@ pub enum EditorMode {
     Normal,
     Insert,
     Calendar,
+    DateInput,
+    Visual,
+    VisualLine,
 }
 
 pub struct Editor<'e> {
@@ -33,7 +42,7 @@ pub struct Editor<'e> {
 impl<'e> Editor<'e> {
     pub fn new() -> Self {
         Self {
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         }
     }
 }
@@ -43,10 +52,15 @@ pub struct EditorState<'e> {
     pub mode: EditorMode,
     pub focus: Option<usize>,
     pub date_focus: Option<OffsetDateTime>,
+    pub date_input: TextArea<'e>,
+    pub date_input_error: bool,
     pub fields: Vec<TextArea<'e>>,
     pub dirty_fields: Vec<usize>,
     pub mod_key: Option<(KeyEvent, u32)>,
     pub is_modified: bool,
+    /// Subtasks turned into TODOs of their own, created on submit
+    pub promoted: Vec<Task>,
+    pub vim: VimState,
 }
 
 fn set_default_styles<'e>(field: &mut TextArea<'e>, is_modified: bool) {
@@ -72,7 +86,7 @@ fn set_default_styles<'e>(field: &mut TextArea<'e>, is_modified: bool) {
 fn build_input_field<'e>(lines: Vec<String>, is_sub: bool) -> TextArea<'e> {
     let mut field = TextArea::new(lines);
     if is_sub {
-        field.set_block(Block::default().padding(Padding::horizontal(2)));
+        field.set_block(Block::default().padding(Padding::new(3, 2, 0, 0)));
     } else {
         field.set_block(
             Block::default()
@@ -85,11 +99,13 @@ fn build_input_field<'e>(lines: Vec<String>, is_sub: bool) -> TextArea<'e> {
     field
 }
 
-impl<'e> EditorState<'e> {
-    pub fn clone_task(&self) -> Task {
-        self.task.clone()
-    }
+fn build_date_input<'e>() -> TextArea<'e> {
+    let mut field = build_input_field(Vec::new(), false);
+    field.set_placeholder_text("tomorrow, fri, +3d...");
+    field
+}
 
+impl<'e> EditorState<'e> {
     pub fn get_focused_mut(&mut self) -> Option<(&mut TextArea<'e>, usize)> {
         if let Some(focus) = self.focus {
             return self.fields.get_mut(focus).map(|f| (f, focus));
@@ -97,16 +113,12 @@ impl<'e> EditorState<'e> {
         None
     }
 
-    pub fn move_cursor(&mut self, m: CursorMove) {
-        if let Some((textarea, _)) = self.get_focused_mut() {
-            textarea.move_cursor(m);
-        }
-    }
-
     pub fn move_date_cursor(&mut self, m: CursorMove) {
-        let date = self
-            .date_focus
-            .get_or_insert(self.task.date.unwrap_or(OffsetDateTime::now_utc()));
+        let date = self.date_focus.get_or_insert(
+            self.task
+                .date
+                .unwrap_or(local_today().midnight().assume_utc()),
+        );
 
         match m {
             CursorMove::Up => *date -= Duration::WEEK,
@@ -129,12 +141,39 @@ impl<'e> EditorState<'e> {
     }
 
     pub fn remove_due_date(&mut self) {
-        if let Some(_) = self.task.date {
+        if self.task.date.is_some() {
             self.is_modified = true;
         }
         self.task.date = None;
     }
 
+    pub fn enter_date_input(&mut self) {
+        self.date_input = build_date_input();
+        self.date_input_error = false;
+        self.mode = EditorMode::DateInput;
+    }
+
+    pub fn exit_date_input(&mut self) {
+        self.date_input = build_date_input();
+        self.date_input_error = false;
+        self.mode = EditorMode::Calendar;
+    }
+
+    /// Set the due date from the date input. Invalid input is kept for
+    /// correcting and marked as an error
+    pub fn submit_date_input(&mut self) {
+        let input = self.date_input.lines().join(" ");
+        match parse_due_date(&input) {
+            Ok(date) => {
+                self.task.date = Some(date);
+                self.date_focus = Some(date);
+                self.is_modified = true;
+                self.exit_date_input();
+            }
+            Err(_) => self.date_input_error = true,
+        }
+    }
+
     pub fn enter_insert_mode(&mut self) {
         self.focus.get_or_insert(0);
         self.mode = EditorMode::Insert;
@@ -155,7 +194,7 @@ impl<'e> EditorState<'e> {
 
     // Decay mod key press TTL
     pub fn decay_mod_key(&mut self) {
-        if let Some(mut mk) = self.mod_key.clone() {
+        if let Some(mut mk) = self.mod_key {
             mk.1 -= 1;
             if mk.1 == 0 {
                 if let Some(focus) = self.focus {
@@ -175,73 +214,145 @@ impl<'e> EditorState<'e> {
         self.is_modified = true;
     }
 
+    fn checklist_mut(&mut self) -> &mut Vec<SubTask> {
+        self.task.checklist.get_or_insert_with(Vec::new)
+    }
+
+    /// Index of the focused subtask in the checklist. The first two fields
+    /// are the name and the notes, so subtask i is in field i + 2
+    fn focused_subtask(&self) -> Option<usize> {
+        self.focus
+            .and_then(|i| i.checked_sub(2))
+            .filter(|i| *i < self.checklist_len())
+    }
+
+    fn checklist_len(&self) -> usize {
+        self.task.checklist.as_ref().map_or(0, |c| c.len())
+    }
+
+    /// Write the edited fields to the task. Subtasks whose text was cleared
+    /// are removed
     pub fn sync_changes(&mut self) {
-        self.dirty_fields.sort();
-        self.dirty_fields.reverse();
+        let mut dirty = std::mem::take(&mut self.dirty_fields);
+        dirty.sort_unstable();
+        dirty.dedup();
 
-        for i in &self.dirty_fields {
+        for &i in &dirty {
+            let Some(content) = self.fields.get(i).map(|f| f.lines().join("\n")) else {
+                continue;
+            };
             match i {
-                0 => self.task.text = self.fields[0].lines().join("\n"),
-                1 => {
-                    let content = self.fields[1].lines().join("\n");
-                    if content.len() > 0 {
-                        self.task.notes = Some(content);
-                    } else {
-                        self.task.notes = None;
+                0 => self.task.text = content,
+                1 => self.task.notes = Some(content).filter(|n| !n.is_empty()),
+                n => {
+                    if let Some(sub) = self.checklist_mut().get_mut(n - 2) {
+                        sub.text = content;
                     }
                 }
-                n if *n < self.fields.len() => {
-                    let list = self
-                        .task
-                        .checklist
-                        .clone()
-                        .or_else(|| Some(Vec::new()))
-                        .map(|mut v| {
-                            let content = self.fields[*n].lines().join("\n");
-                            if content.len() > 0 {
-                                v[(*n) - 2].text = content;
-                            } else {
-                                v.remove((*n) - 2);
-                                self.fields.remove(*n);
-                            }
-                            v
-                        });
-                    self.task.checklist = list;
-                }
-                _ => {}
+            }
+        }
+
+        for &i in dirty.iter().rev() {
+            if i >= 2 && self.fields.get(i).is_some_and(|f| f.is_empty()) {
+                self.remove_subtask_at(i - 2);
             }
         }
     }
 
-    pub fn insert_subtask(&mut self) {
-        let field = build_input_field(Vec::new(), true);
-        let index: usize;
-        if let Some(i) = self.focus {
-            index = max(2, i + 1);
-        } else {
-            index = 2;
+    fn remove_subtask_at(&mut self, index: usize) -> Option<SubTask> {
+        if index >= self.checklist_len() {
+            return None;
         }
+        self.fields.remove(index + 2);
+        let removed = self.checklist_mut().remove(index);
+        self.focus = self.focus.map(|f| f.min(self.fields.len() - 1));
+        self.is_modified = true;
+
+        Some(removed)
+    }
+
+    /// Insert a subtask along with its field at the given field index
+    fn insert_subtask_field(&mut self, index: usize, sub: SubTask) {
+        let index = index.clamp(2, self.fields.len());
+        self.fields
+            .insert(index, build_input_field(vec![sub.text.clone()], true));
+        self.checklist_mut().insert(index - 2, sub);
+    }
+
+    pub fn insert_subtask(&mut self) {
+        self.sync_changes();
+        let index = self.focus.map_or(2, |i| (i + 1).max(2));
+
+        self.insert_subtask_field(
+            index,
+            SubTask {
+                text: "".into(),
+                completed: false,
+            },
+        );
         self.focus = Some(index);
-        self.fields.insert(index, field);
         self.dirty_fields.push(index);
-        let list = self
-            .task
-            .checklist
-            .clone()
-            .or_else(|| Some(Vec::new()))
-            .map(|mut v| {
-                v.insert(
-                    index - 2,
-                    SubTask {
-                        text: "".into(),
-                        completed: false,
-                    },
-                );
-                v
+        self.mode = EditorMode::Insert;
+    }
+
+    pub fn toggle_subtask(&mut self) {
+        if let Some(i) = self.focused_subtask() {
+            let sub = &mut self.checklist_mut()[i];
+            sub.completed = !sub.completed;
+            self.is_modified = true;
+        }
+    }
+
+    /// Move the focused subtask up or down in the checklist
+    pub fn move_subtask(&mut self, direction: Direction) {
+        let Some(i) = self.focused_subtask() else {
+            return;
+        };
+        let target = match direction {
+            Direction::UP => i.checked_sub(1),
+            Direction::DOWN => Some(i + 1).filter(|t| *t < self.checklist_len()),
+            _ => None,
+        };
+        let Some(target) = target else {
+            return;
+        };
+
+        self.sync_changes();
+        self.fields.swap(i + 2, target + 2);
+        self.checklist_mut().swap(i, target);
+        self.focus = Some(target + 2);
+        self.is_modified = true;
+    }
+
+    pub fn remove_subtask(&mut self) {
+        if let Some(i) = self.focused_subtask() {
+            self.sync_changes();
+            self.remove_subtask_at(i);
+        }
+    }
+
+    /// Remove the focused subtask from the checklist and turn it into a TODO
+    /// of its own with the tags of the task
+    pub fn promote_subtask(&mut self) {
+        let Some(i) = self.focused_subtask() else {
+            return;
+        };
+        self.sync_changes();
+        if let Some(sub) = self.remove_subtask_at(i) {
+            self.promoted.push(Task {
+                text: sub.text,
+                tags: self.task.tags.clone(),
+                ..Task::default()
             });
+        }
+    }
 
-        self.mode = EditorMode::Insert;
-        self.task.checklist = list;
+    /// The edited task along with the TODOs promoted from its checklist
+    pub fn take_submission(&mut self) -> Vec<Task> {
+        self.sync_changes();
+        let mut tasks = vec![self.task.clone()];
+        tasks.append(&mut self.promoted);
+        tasks
     }
 
     pub fn new(task_option: Option<&Task>) -> Self {
@@ -284,10 +395,14 @@ impl<'e> EditorState<'e> {
             mode,
             focus: Some(0),
             date_focus: None,
+            date_input: build_date_input(),
+            date_input_error: false,
             fields,
             dirty_fields: Vec::new(),
             mod_key: None,
             is_modified: false,
+            promoted: Vec::new(),
+            vim: VimState::default(),
         }
     }
 }
@@ -310,7 +425,7 @@ impl<'e> StatefulWidget for Editor<'e> {
 
         let chunks = Layout::vertical(constraints).split(left_col);
 
-        Clear::default().render(area, buf);
+        Clear.render(area, buf);
 
         // Popup card
         let card_bg: Color = if state.is_modified {
@@ -332,14 +447,40 @@ impl<'e> StatefulWidget for Editor<'e> {
         for (i, textarea) in state.fields.iter_mut().enumerate() {
             if let Some(s) = state.focus {
                 set_default_styles(textarea, state.is_modified);
-                if i == s && state.mode != EditorMode::Calendar {
+                if i == s
+                    && matches!(
+                        state.mode,
+                        EditorMode::Normal
+                            | EditorMode::Insert
+                            | EditorMode::Visual
+                            | EditorMode::VisualLine
+                    )
+                {
                     textarea.set_cursor_style(cursor_style);
                 };
             }
             if let Some(block) = textarea.block().cloned() {
                 textarea.set_block(block.border_style(border_bg));
             };
+            let completed = i
+                .checked_sub(2)
+                .and_then(|s| state.task.checklist.as_ref()?.get(s))
+                .map(|s| s.completed);
+            if completed == Some(true) {
+                textarea.set_style(
+                    textarea
+                        .style()
+                        .fg(Palette::FG.into())
+                        .add_modifier(Modifier::CROSSED_OUT | Modifier::DIM),
+                );
+            }
             textarea.render(chunks[i], buf);
+
+            // Completion marker in the left padding of the subtask
+            if let Some(completed) = completed {
+                let marker = if completed { "✓" } else { "·" };
+                buf.set_string(chunks[i].x + 1, chunks[i].y, marker, Style::default());
+            }
         }
 
         let checklist_area = Rect {
@@ -348,19 +489,48 @@ impl<'e> StatefulWidget for Editor<'e> {
             width: chunks[0].width,
             height: chunks[chunks.len() - 1].y + chunks[chunks.len() - 1].height - chunks[1].y - 1,
         };
-        Block::bordered()
-            .border_style(border_bg)
-            .render(checklist_area, buf);
+        let mut checklist_block = Block::bordered().border_style(border_bg);
+        if let Some(checklist) = state.task.checklist.as_ref().filter(|c| !c.is_empty()) {
+            let n_done = checklist.iter().filter(|s| s.completed).count();
+            checklist_block = checklist_block.title(
+                Line::from(format!(" {n_done}/{} ", checklist.len()))
+                    .fg(Palette::FG)
+                    .right_aligned(),
+            );
+        }
+        checklist_block.render(checklist_area, buf);
+
+        // Visual mode and partially typed commands under the checklist
+        let mode = match state.mode {
+            EditorMode::Visual => "-- VISUAL --",
+            EditorMode::VisualLine => "-- VISUAL LINE --",
+            _ => "",
+        };
+        let status = format!("{mode} {}", state.vim.pending_keys());
+        let status_area = Rect {
+            y: checklist_area.y + checklist_area.height,
+            height: 1,
+            ..checklist_area
+        };
+        if status_area.bottom() <= area.bottom() {
+            Line::from(status.trim().to_string())
+                .fg(Palette::FG)
+                .right_aligned()
+                .render(status_area, buf);
+        }
 
-        let [cal_area, diff_area] =
-            Layout::vertical([Constraint::Length(7), Constraint::Fill(1)]).areas(right_col);
+        let [cal_area, date_input_area, diff_area] = Layout::vertical([
+            Constraint::Length(7),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+        ])
+        .areas(right_col);
 
         let mut event_store = CalendarEventStore::default();
 
-        state
-            .task
-            .date
-            .map(|d| event_store.add(d.date(), Style::default().bg(border_bg)));
+        if let Some(d) = state.task.date {
+            event_store.add(d.date(), Style::default().bg(border_bg))
+        }
 
         let date = if let Some(focus) = state.date_focus {
             focus.date()
@@ -368,7 +538,7 @@ impl<'e> StatefulWidget for Editor<'e> {
             if let Some(due) = state.task.date {
                 due.date()
             } else {
-                OffsetDateTime::now_utc().date()
+                local_today()
             }
         };
 
@@ -386,6 +556,19 @@ impl<'e> StatefulWidget for Editor<'e> {
             .show_month_header(Style::default())
             .render(cal_area, buf);
 
+        let date_input = &mut state.date_input;
+        set_default_styles(date_input, state.is_modified);
+        if state.date_input_error {
+            date_input.set_style(Style::default().bg(Palette::RED.into()));
+        }
+        if state.mode == EditorMode::DateInput {
+            date_input.set_cursor_style(cursor_style);
+        }
+        if let Some(block) = date_input.block().cloned() {
+            date_input.set_block(block.border_style(border_bg));
+        };
+        date_input.render(date_input_area, buf);
+
         let diff_chunks: Vec<Rect> =
             Layout::vertical([Constraint::Length(3), Constraint::Length(3)])
                 .horizontal_margin(2)
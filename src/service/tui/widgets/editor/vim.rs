@@ -0,0 +1,858 @@
+use std::sync::RwLock;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use tui_textarea::{CursorMove, TextArea};
+
+use super::{EditorMode, EditorState};
+use crate::service::{tui::util::Direction, types::SubTask};
+
+// Yanked and deleted text, shared by all fields and editors
+static REGISTERS: RwLock<Vec<(char, Register)>> = RwLock::new(Vec::new());
+
+const UNNAMED_REGISTER: char = '"';
+
+#[derive(Clone)]
+struct Register {
+    text: String,
+    /// Whether the text is whole lines, which are put on lines of their own
+    linewise: bool,
+}
+
+fn get_register(name: char) -> Option<Register> {
+    let registers = REGISTERS.read().ok()?;
+    registers
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, r)| r.clone())
+}
+
+/// Store text in a register. The unnamed register always holds the latest
+/// text, as in vim
+fn set_register(name: char, register: Register) {
+    let Ok(mut registers) = REGISTERS.write() else {
+        return;
+    };
+    for name in [name, UNNAMED_REGISTER] {
+        registers.retain(|(n, _)| *n != name);
+        registers.push((name, register.clone()));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+impl Operator {
+    fn from_key(c: char) -> Option<Self> {
+        match c {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        }
+    }
+}
+
+fn motion(c: char) -> Option<CursorMove> {
+    let motion = match c {
+        'h' => CursorMove::Back,
+        'l' => CursorMove::Forward,
+        'w' => CursorMove::WordForward,
+        'b' => CursorMove::WordBack,
+        'e' => CursorMove::WordEnd,
+        '0' => CursorMove::Head,
+        '$' => CursorMove::End,
+        _ => return None,
+    };
+    Some(motion)
+}
+
+fn line_len(textarea: &TextArea, row: usize) -> usize {
+    textarea.lines().get(row).map_or(0, |l| l.chars().count())
+}
+
+fn jump(textarea: &mut TextArea, row: usize, col: usize) {
+    textarea.move_cursor(CursorMove::Jump(row as u16, col as u16));
+}
+
+/// Select the lines from..=to of a textarea
+fn select_lines(textarea: &mut TextArea, from: usize, to: usize) {
+    textarea.cancel_selection();
+    jump(textarea, from, 0);
+    textarea.start_selection();
+    jump(textarea, to, line_len(textarea, to));
+}
+
+/// Columns of the word under the cursor as a range. A word is a run of word
+/// characters, of other non-blank characters or of whitespace. Around a word
+/// also includes the whitespace after it, or before it if there is none after
+fn word_bounds(line: &str, col: usize, around: bool) -> Option<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return None;
+    }
+    let class = |c: char| match c {
+        c if c.is_whitespace() => 0,
+        c if c.is_alphanumeric() || c == '_' => 1,
+        _ => 2,
+    };
+    let col = col.min(chars.len() - 1);
+    let kind = class(chars[col]);
+
+    let mut start = col;
+    while start > 0 && class(chars[start - 1]) == kind {
+        start -= 1;
+    }
+    let mut end = col + 1;
+    while end < chars.len() && class(chars[end]) == kind {
+        end += 1;
+    }
+
+    if around {
+        let word_end = end;
+        // Around whitespace includes the word after it
+        let next_kind = if kind == 0 { None } else { Some(0) };
+        let extend_kind = next_kind.or(chars.get(end).map(|c| class(*c)));
+        while end < chars.len() && Some(class(chars[end])) == extend_kind {
+            end += 1;
+        }
+        if end == word_end && kind != 0 {
+            while start > 0 && class(chars[start - 1]) == 0 {
+                start -= 1;
+            }
+        }
+    }
+
+    Some((start, end))
+}
+
+/// State of the vim emulation of the editor
+#[derive(Default)]
+pub struct VimState {
+    count: Option<usize>,
+    register: Option<char>,
+    awaiting_register: bool,
+    operator: Option<(Operator, usize)>,
+    text_object: Option<char>,
+    /// Keys of the command being typed, kept for repeating it with `.`
+    keys: Vec<KeyEvent>,
+    /// Whether the command being typed changes the task
+    is_change: bool,
+    last_change: Vec<KeyEvent>,
+    replaying: bool,
+    /// Start of the selection in visual mode
+    anchor: (usize, usize),
+    /// Line of the cursor in visual line mode
+    visual_row: usize,
+}
+
+impl VimState {
+    /// Whether a command is partially typed
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.awaiting_register || self.operator.is_some()
+    }
+
+    /// Keys of the partially typed command, for showing them
+    pub fn pending_keys(&self) -> String {
+        if !self.is_pending() {
+            return String::new();
+        }
+        self.keys
+            .iter()
+            .filter_map(|k| match k.code {
+                KeyCode::Char(c) => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn push_count_digit(&mut self, digit: char) {
+        let digit = digit.to_digit(10).unwrap_or(0) as usize;
+        self.count = Some(self.count.unwrap_or(0).saturating_mul(10) + digit);
+    }
+
+    fn take_count(&mut self) -> usize {
+        self.count.take().unwrap_or(1).max(1)
+    }
+
+    fn take_register(&mut self) -> char {
+        self.register.take().unwrap_or(UNNAMED_REGISTER)
+    }
+
+    fn reset(&mut self) {
+        self.count = None;
+        self.register = None;
+        self.awaiting_register = false;
+        self.operator = None;
+        self.text_object = None;
+    }
+}
+
+impl<'e> EditorState<'e> {
+    fn focused(&mut self) -> Option<&mut TextArea<'e>> {
+        self.get_focused_mut().map(|(textarea, _)| textarea)
+    }
+
+    fn mark_focus_modified(&mut self) {
+        if let Some(i) = self.focus {
+            self.mark_modified(i);
+        }
+    }
+
+    /// Handle a key in the normal, visual and insert modes of the editor
+    pub fn handle_vim_key(&mut self, key: KeyEvent) {
+        // Keys typed in insert mode are part of a change only if the insert
+        // mode was entered with a command
+        let records = self.mode != EditorMode::Insert || !self.vim.keys.is_empty();
+        if records && !self.vim.replaying {
+            self.vim.keys.push(key);
+        }
+
+        match self.mode {
+            EditorMode::Normal => self.handle_normal_key(key),
+            EditorMode::Visual | EditorMode::VisualLine => self.handle_visual_key(key),
+            EditorMode::Insert => self.handle_insert_key(key),
+            _ => {}
+        }
+
+        // A command is complete once back in normal mode with nothing pending
+        if self.mode == EditorMode::Normal && !self.vim.is_pending() {
+            let keys = std::mem::take(&mut self.vim.keys);
+            if self.vim.is_change && !self.vim.replaying {
+                self.vim.last_change = keys;
+            }
+            self.vim.is_change = false;
+        }
+    }
+
+    fn handle_insert_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.exit_insert_mode(),
+            KeyCode::Enter => match self.focus {
+                Some(i) if i < self.fields.len() - 1 => self.focus = Some(i + 1),
+                Some(_) => self.insert_subtask(),
+                None => {}
+            },
+            KeyCode::Tab => {
+                let mut i = *self.focus.get_or_insert(0);
+                if key.modifiers == KeyModifiers::SHIFT {
+                    i = i.saturating_sub(1);
+                } else {
+                    i += 1;
+                }
+                self.focus = Some(i.clamp(0, self.fields.len() - 1));
+            }
+            // Exit insert mode with jj
+            KeyCode::Char('j') => match self.mod_key {
+                Some((
+                    KeyEvent {
+                        code: KeyCode::Char('j'),
+                        ..
+                    },
+                    _,
+                )) => {
+                    self.mod_key = None;
+                    self.exit_insert_mode();
+                }
+                _ => self.add_mod_key(key),
+            },
+            _ => {
+                let mod_key = self.pop_mod_key();
+                if let Some((textarea, i)) = self.get_focused_mut() {
+                    if let Some(pending) = mod_key {
+                        textarea.input(pending);
+                    }
+                    textarea.input(key);
+                    self.mark_modified(i);
+                }
+            }
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) {
+        let KeyCode::Char(c) = key.code else {
+            match key.code {
+                KeyCode::Tab => self.move_focus(1),
+                KeyCode::Esc => self.vim.reset(),
+                _ => {}
+            }
+            return;
+        };
+
+        if self.vim.awaiting_register {
+            self.vim.awaiting_register = false;
+            if c.is_ascii_alphabetic() || c == UNNAMED_REGISTER {
+                self.vim.register = Some(c.to_ascii_lowercase());
+            } else {
+                self.vim.reset();
+            }
+            return;
+        }
+        if let Some((op, count)) = self.vim.operator {
+            self.handle_operator_key(op, count, c);
+            return;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match c {
+            '0'..='9' if c != '0' || self.vim.count.is_some() => {
+                self.vim.push_count_digit(c);
+                return;
+            }
+            '"' => {
+                self.vim.awaiting_register = true;
+                return;
+            }
+            _ if Operator::from_key(c).is_some() && !ctrl => {
+                let count = self.vim.take_count();
+                self.vim.operator = Operator::from_key(c).map(|op| (op, count));
+                return;
+            }
+            'r' if ctrl => {
+                let count = self.vim.take_count();
+                let redone = self.focused().map(|t| (0..count).any(|_| t.redo()));
+                if redone == Some(true) {
+                    self.mark_focus_modified();
+                }
+            }
+            'l' if ctrl => self.mode = EditorMode::Calendar,
+            'j' if alt => {
+                for _ in 0..self.vim.take_count() {
+                    self.move_subtask(Direction::DOWN);
+                }
+                self.vim.is_change = true;
+            }
+            'k' if alt => {
+                for _ in 0..self.vim.take_count() {
+                    self.move_subtask(Direction::UP);
+                }
+                self.vim.is_change = true;
+            }
+            'j' => {
+                let count = self.vim.take_count();
+                self.move_focus(count as isize);
+            }
+            'k' => {
+                let count = self.vim.take_count();
+                self.move_focus(-(count as isize));
+            }
+            'i' | 'a' | 'A' | 'I' => {
+                let m = match c {
+                    'a' => Some(CursorMove::Forward),
+                    'A' => Some(CursorMove::End),
+                    'I' => Some(CursorMove::Head),
+                    _ => None,
+                };
+                if let (Some(m), Some(textarea)) = (m, self.focused()) {
+                    textarea.move_cursor(m);
+                }
+                self.enter_insert_mode();
+                self.vim.is_change = true;
+            }
+            'o' | 'O' => {
+                self.insert_subtask();
+                self.vim.is_change = true;
+            }
+            'x' => self.delete_chars(),
+            'p' => self.put(false),
+            'P' => self.put(true),
+            'u' => {
+                let count = self.vim.take_count();
+                let undone = self.focused().map(|t| (0..count).any(|_| t.undo()));
+                if undone == Some(true) {
+                    self.mark_focus_modified();
+                }
+            }
+            '.' => self.repeat_last_change(),
+            'v' => self.enter_visual_mode(false),
+            'V' => self.enter_visual_mode(true),
+            ' ' => {
+                self.toggle_subtask();
+                self.vim.is_change = true;
+            }
+            'D' => {
+                for _ in 0..self.vim.take_count() {
+                    self.remove_subtask();
+                }
+                self.vim.is_change = true;
+            }
+            'T' => self.promote_subtask(),
+            '+' => self.next_task_difficulty(),
+            '-' => self.prev_task_difficulty(),
+            c => {
+                if let Some(m) = motion(c) {
+                    let count = self.vim.take_count();
+                    if let Some(textarea) = self.focused() {
+                        for _ in 0..count {
+                            textarea.move_cursor(m);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.vim.reset();
+    }
+
+    fn move_focus(&mut self, by: isize) {
+        let last = self.fields.len().saturating_sub(1);
+        let focus = self
+            .focus
+            .map_or(0, |i| i.saturating_add_signed(by).min(last));
+        self.focus = Some(focus);
+    }
+
+    fn handle_operator_key(&mut self, op: Operator, count: usize, c: char) {
+        if let Some(object) = self.vim.text_object.take() {
+            if c == 'w' {
+                self.apply_to_word(op, object == 'a');
+            }
+            self.vim.reset();
+            return;
+        }
+
+        match c {
+            '0'..='9' if c != '0' || self.vim.count.is_some() => {
+                self.vim.push_count_digit(c);
+                return;
+            }
+            'i' | 'a' => {
+                self.vim.text_object = Some(c);
+                return;
+            }
+            _ => {}
+        }
+
+        // Counts before and after the operator multiply, e.g. 2d3w
+        let count = count * self.vim.take_count();
+        if Operator::from_key(c) == Some(op) {
+            self.apply_to_lines(op, count);
+        } else if op == Operator::Change && c == 'w' {
+            // cw changes to the end of the word like ce, as in vim
+            self.apply_to_motion(op, CursorMove::WordEnd, count, true);
+        } else if let Some(m) = motion(c) {
+            self.apply_to_motion(op, m, count, c == 'e');
+        }
+
+        self.vim.reset();
+    }
+
+    /// Apply an operator to the selection of the focused field
+    fn apply_to_selection(&mut self, op: Operator, linewise: bool) {
+        let register = self.vim.take_register();
+        let Some((textarea, i)) = self.get_focused_mut() else {
+            return;
+        };
+        let Some((start, _)) = textarea.selection_range() else {
+            textarea.cancel_selection();
+            return;
+        };
+
+        let modified = match op {
+            Operator::Yank => {
+                textarea.copy();
+                jump(textarea, start.0, start.1);
+                false
+            }
+            Operator::Delete | Operator::Change => textarea.cut(),
+        };
+        let text = textarea.yank_text();
+        set_register(register, Register { text, linewise });
+
+        if modified {
+            self.mark_modified(i);
+        }
+        if op != Operator::Yank {
+            self.vim.is_change = true;
+        }
+        match op {
+            Operator::Change => self.enter_insert_mode(),
+            _ => self.mode = EditorMode::Normal,
+        }
+    }
+
+    fn apply_to_motion(&mut self, op: Operator, m: CursorMove, count: usize, inclusive: bool) {
+        if let Some(textarea) = self.focused() {
+            textarea.start_selection();
+            for _ in 0..count {
+                textarea.move_cursor(m);
+            }
+            if inclusive {
+                textarea.move_cursor(CursorMove::Forward);
+            }
+        }
+        self.apply_to_selection(op, false);
+    }
+
+    fn apply_to_word(&mut self, op: Operator, around: bool) {
+        let Some(textarea) = self.focused() else {
+            return;
+        };
+        let (row, col) = textarea.cursor();
+        let Some((start, end)) = word_bounds(&textarea.lines()[row], col, around) else {
+            return;
+        };
+
+        jump(textarea, row, start);
+        textarea.start_selection();
+        jump(textarea, row, end);
+        self.apply_to_selection(op, false);
+    }
+
+    /// Apply an operator to count lines from the cursor, as with dd, cc and
+    /// yy. Subtasks are lines of the checklist, so in the checklist the
+    /// operator applies to count subtasks
+    fn apply_to_lines(&mut self, op: Operator, count: usize) {
+        match self.focused_subtask() {
+            Some(i) if op != Operator::Change => {
+                self.sync_changes();
+                let end = (i + count).min(self.checklist_len());
+                let checklist = self.task.checklist.as_deref().unwrap_or_default();
+                let lines: Vec<String> = checklist[i..end].iter().map(|s| s.text.clone()).collect();
+                set_register(
+                    self.vim.take_register(),
+                    Register {
+                        text: lines.join("\n"),
+                        linewise: true,
+                    },
+                );
+                if op == Operator::Delete {
+                    for _ in i..end {
+                        self.remove_subtask_at(i);
+                    }
+                    self.vim.is_change = true;
+                }
+            }
+            _ => {
+                let Some(textarea) = self.focused() else {
+                    return;
+                };
+                let row = textarea.cursor().0;
+                self.apply_to_text_lines(op, row, row + count - 1);
+            }
+        }
+    }
+
+    /// Apply an operator to the lines from..=to of the focused field
+    fn apply_to_text_lines(&mut self, op: Operator, from: usize, to: usize) {
+        let Some(textarea) = self.focused() else {
+            return;
+        };
+        let last = textarea.lines().len() - 1;
+        let to = to.min(last);
+        let text = textarea.lines()[from..=to].join("\n");
+
+        match op {
+            Operator::Yank => {
+                textarea.cancel_selection();
+                jump(textarea, from, 0);
+            }
+            // Keep the line break of the line after or before the lines
+            Operator::Delete if to < last => {
+                textarea.cancel_selection();
+                jump(textarea, from, 0);
+                textarea.start_selection();
+                jump(textarea, to + 1, 0);
+                textarea.cut();
+            }
+            Operator::Delete if from > 0 => {
+                textarea.cancel_selection();
+                jump(textarea, from - 1, line_len(textarea, from - 1));
+                textarea.start_selection();
+                jump(textarea, to, line_len(textarea, to));
+                textarea.cut();
+                textarea.move_cursor(CursorMove::Head);
+            }
+            Operator::Delete | Operator::Change => {
+                select_lines(textarea, from, to);
+                textarea.cut();
+            }
+        }
+        set_register(
+            self.vim.take_register(),
+            Register {
+                text,
+                linewise: true,
+            },
+        );
+
+        if op != Operator::Yank {
+            self.mark_focus_modified();
+            self.vim.is_change = true;
+        }
+        match op {
+            Operator::Change => self.enter_insert_mode(),
+            // Clearing a subtask removes it
+            Operator::Delete => {
+                self.mode = EditorMode::Normal;
+                self.sync_changes();
+            }
+            Operator::Yank => self.mode = EditorMode::Normal,
+        }
+    }
+
+    fn delete_chars(&mut self) {
+        let count = self.vim.take_count();
+        let Some(textarea) = self.focused() else {
+            return;
+        };
+        let (row, col) = textarea.cursor();
+        let n = count.min(line_len(textarea, row).saturating_sub(col));
+        if n == 0 {
+            return;
+        }
+
+        textarea.start_selection();
+        for _ in 0..n {
+            textarea.move_cursor(CursorMove::Forward);
+        }
+        self.apply_to_selection(Operator::Delete, false);
+    }
+
+    /// Put the text of a register after or before the cursor. Lines are put
+    /// on lines of their own, which in the checklist are new subtasks
+    fn put(&mut self, before: bool) {
+        let count = self.vim.take_count();
+        let Some(register) = get_register(self.vim.take_register()) else {
+            return;
+        };
+        let lines: Vec<&str> = register.text.split('\n').collect();
+        let lines = lines.repeat(count);
+
+        match self.focus {
+            Some(i) if i >= 2 && register.linewise => {
+                self.sync_changes();
+                let index = if before { i } else { i + 1 };
+                for (n, line) in lines.iter().enumerate() {
+                    self.insert_subtask_field(
+                        index + n,
+                        SubTask {
+                            text: line.to_string(),
+                            completed: false,
+                        },
+                    );
+                }
+                self.focus = Some(index);
+                self.is_modified = true;
+            }
+            Some(i) => {
+                let Some(textarea) = self.fields.get_mut(i) else {
+                    return;
+                };
+                if register.linewise && i == 1 {
+                    if before {
+                        textarea.move_cursor(CursorMove::Head);
+                        textarea.insert_str(format!("{}\n", lines.join("\n")));
+                        textarea.move_cursor(CursorMove::Up);
+                    } else {
+                        textarea.move_cursor(CursorMove::End);
+                        textarea.insert_str(format!("\n{}", lines.join("\n")));
+                        textarea.move_cursor(CursorMove::Head);
+                    }
+                } else {
+                    // The name is a single line
+                    let separator = if i == 0 { " " } else { "\n" };
+                    let (row, col) = textarea.cursor();
+                    if !before && col < line_len(textarea, row) {
+                        textarea.move_cursor(CursorMove::Forward);
+                    }
+                    let text = match register.linewise {
+                        true => lines.join(separator),
+                        false => register.text.repeat(count),
+                    };
+                    textarea.insert_str(text);
+                }
+                self.mark_modified(i);
+            }
+            None => {}
+        }
+        self.vim.is_change = true;
+    }
+
+    fn repeat_last_change(&mut self) {
+        let count = self.vim.take_count();
+        let keys = self.vim.last_change.clone();
+
+        self.vim.replaying = true;
+        for _ in 0..count {
+            for key in &keys {
+                self.handle_vim_key(*key);
+            }
+        }
+        self.vim.replaying = false;
+    }
+
+    fn enter_visual_mode(&mut self, line: bool) {
+        let Some(textarea) = self.focused() else {
+            return;
+        };
+        let cursor = textarea.cursor();
+        if line {
+            select_lines(textarea, cursor.0, cursor.0);
+        } else {
+            textarea.start_selection();
+        }
+
+        self.vim.anchor = cursor;
+        self.vim.visual_row = cursor.0;
+        self.mode = match line {
+            true => EditorMode::VisualLine,
+            false => EditorMode::Visual,
+        };
+    }
+
+    fn exit_visual_mode(&mut self) {
+        if let Some(textarea) = self.focused() {
+            textarea.cancel_selection();
+        }
+        self.vim.reset();
+        self.mode = EditorMode::Normal;
+    }
+
+    fn move_visual_cursor(&mut self, c: char, line: bool) {
+        let count = self.vim.take_count();
+        let (anchor_row, visual_row) = (self.vim.anchor.0, self.vim.visual_row);
+        let Some(textarea) = self.focused() else {
+            return;
+        };
+
+        if line {
+            let last = textarea.lines().len() - 1;
+            let row = match c {
+                'j' => (visual_row + count).min(last),
+                'k' => visual_row.saturating_sub(count),
+                _ => return,
+            };
+            select_lines(textarea, anchor_row.min(row), anchor_row.max(row));
+            self.vim.visual_row = row;
+            return;
+        }
+
+        let m = match c {
+            'j' => Some(CursorMove::Down),
+            'k' => Some(CursorMove::Up),
+            c => motion(c),
+        };
+        if let Some(m) = m {
+            for _ in 0..count {
+                textarea.move_cursor(m);
+            }
+        }
+    }
+
+    fn handle_visual_key(&mut self, key: KeyEvent) {
+        let line = self.mode == EditorMode::VisualLine;
+        let c = match key.code {
+            KeyCode::Char(c) => c,
+            KeyCode::Esc => {
+                self.exit_visual_mode();
+                return;
+            }
+            _ => return,
+        };
+
+        if self.vim.awaiting_register {
+            self.vim.awaiting_register = false;
+            if c.is_ascii_alphabetic() || c == UNNAMED_REGISTER {
+                self.vim.register = Some(c.to_ascii_lowercase());
+            }
+            return;
+        }
+
+        let op = match c {
+            '0'..='9' if c != '0' || self.vim.count.is_some() => {
+                self.vim.push_count_digit(c);
+                return;
+            }
+            '"' => {
+                self.vim.awaiting_register = true;
+                return;
+            }
+            'v' | 'V' if line == (c == 'V') => {
+                self.exit_visual_mode();
+                return;
+            }
+            'd' | 'x' => Operator::Delete,
+            'c' => Operator::Change,
+            'y' => Operator::Yank,
+            _ => {
+                self.move_visual_cursor(c, line);
+                return;
+            }
+        };
+
+        if line {
+            let (from, to) = {
+                let (anchor, row) = (self.vim.anchor.0, self.vim.visual_row);
+                (anchor.min(row), anchor.max(row))
+            };
+            self.apply_to_text_lines(op, from, to);
+        } else {
+            // The selection includes the character under the cursor, as in vim
+            let (anchor, cursor) = (self.vim.anchor, self.focused().map(|t| t.cursor()));
+            let Some(cursor) = cursor else {
+                return;
+            };
+            let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+            if let Some(textarea) = self.focused() {
+                textarea.cancel_selection();
+                jump(textarea, start.0, start.1);
+                textarea.start_selection();
+                jump(textarea, end.0, end.1);
+                if end.1 < line_len(textarea, end.0) {
+                    textarea.move_cursor(CursorMove::Forward);
+                }
+            }
+            self.apply_to_selection(op, false);
+        }
+        self.vim.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = "let foo_bar = baz;";
+
+    #[test]
+    fn finds_inner_word() {
+        assert_eq!(word_bounds(LINE, 5, false), Some((4, 11)));
+        assert_eq!(word_bounds(LINE, 12, false), Some((12, 13)));
+        assert_eq!(word_bounds(LINE, 3, false), Some((3, 4)));
+        assert_eq!(word_bounds(LINE, 17, false), Some((17, 18)));
+    }
+
+    #[test]
+    fn includes_whitespace_after_word() {
+        assert_eq!(word_bounds(LINE, 5, true), Some((4, 12)));
+        assert_eq!(word_bounds(LINE, 12, true), Some((12, 14)));
+    }
+
+    #[test]
+    fn includes_word_after_whitespace() {
+        assert_eq!(word_bounds(LINE, 3, true), Some((3, 11)));
+    }
+
+    #[test]
+    fn includes_whitespace_before_word_without_whitespace_after() {
+        assert_eq!(word_bounds(LINE, 15, true), Some((13, 17)));
+        assert_eq!(word_bounds("foo bar", 5, true), Some((3, 7)));
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        assert_eq!(word_bounds("süß ist", 1, false), Some((0, 3)));
+        assert_eq!(word_bounds("süß ist", 1, true), Some((0, 4)));
+    }
+
+    #[test]
+    fn clamps_cursor_to_line() {
+        assert_eq!(word_bounds("foo", 10, false), Some((0, 3)));
+        assert_eq!(word_bounds("", 0, false), None);
+    }
+}
@@ -1,22 +1,34 @@
 use std::{
-    cmp::max,
+    cmp::{max, Ordering},
     collections::{HashMap, HashSet},
-    mem,
+    fmt, mem,
 };
 
+use serde::{Deserialize, Serialize};
 use sqlx::types::uuid::Uuid;
 
 use crossterm::event::KeyEvent;
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Layout, Margin, Rect},
-    style::Style,
-    widgets::{Block, Padding, Paragraph, StatefulWidget, Widget},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{
+        Block, BorderType, Borders, Cell, Padding, Paragraph, Row, StatefulWidget, Table,
+        TableState, Widget,
+    },
 };
 
+use time::{format_description::well_known::Iso8601, Duration, OffsetDateTime};
+
 use crate::service::{
-    tui::util::{Direction, Palette, MOD_KEY_TTL},
+    reorder::{plan_moves, priority_order},
+    tui::{
+        markdown::{line_width, render_inline, render_markdown, truncate_line, wrap_line},
+        util::{priority_color, Direction, Palette, MOD_KEY_TTL},
+    },
     types::{Action, SubTask, Task},
+    width::display_width,
 };
 
 const GRID_WIDTH: usize = 3;
@@ -25,29 +37,178 @@ const GRID_SIZE: usize = GRID_WIDTH * GRID_HEIGHT;
 
 type Diff = HashSet<Action>;
 
+/// Order in which the grid shows the tasks. Only the server view reflects the
+/// actual order of the tasks in Habitica, the rest are sorted client side
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum GridView {
+    #[default]
+    Server,
+    Due,
+    Priority,
+    Difficulty,
+    Checklist,
+}
+
+impl GridView {
+    fn next(self) -> Self {
+        match self {
+            GridView::Server => GridView::Due,
+            GridView::Due => GridView::Priority,
+            GridView::Priority => GridView::Difficulty,
+            GridView::Difficulty => GridView::Checklist,
+            GridView::Checklist => GridView::Server,
+        }
+    }
+}
+
+impl fmt::Display for GridView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GridView::Server => "server order",
+            GridView::Due => "due date",
+            GridView::Priority => "priority",
+            GridView::Difficulty => "difficulty",
+            GridView::Checklist => "checklist progress",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How the tasks are laid out, either as a page of cards or as a table with a
+/// row per task
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridLayout {
+    #[default]
+    Cards,
+    Table,
+}
+
+/// Fraction of completed checklist items, None if the task has no checklist
+fn checklist_progress(task: &Task) -> Option<f64> {
+    let checklist = task.checklist.as_ref().filter(|c| !c.is_empty())?;
+    let n_completed = checklist.iter().filter(|s| s.completed).count();
+    Some(n_completed as f64 / checklist.len() as f64)
+}
+
+/// Lines of a task in a grid cell wrapped to the given width, along with the
+/// index of the subtask each line belongs to
+fn task_lines(task: &Task, completed: bool, width: usize) -> Vec<(Line<'static>, Option<usize>)> {
+    let difficulty = task.difficulty.to_string();
+    let title_width = width.saturating_sub(display_width(&difficulty) + 1);
+    let mut title = render_inline(&task.text, Style::default());
+    if completed {
+        title.push(Span::raw(" ✅"));
+    }
+    let mut title = wrap_line(Line::from(title), title_width, 0);
+    if let Some(first) = title.first_mut() {
+        let gap = width.saturating_sub(line_width(first) + display_width(&difficulty));
+        first.push_span(" ".repeat(gap));
+        first.push_span(difficulty);
+    }
+    let mut lines: Vec<(Line, Option<usize>)> = title.into_iter().map(|l| (l, None)).collect();
+
+    if let Some(notes) = task.notes.as_ref().filter(|n| !n.is_empty()) {
+        lines.extend(render_markdown(notes, width).into_iter().map(|l| (l, None)));
+    }
+    if let Some(date) = task.date.and_then(|d| d.format(&Iso8601::DATE).ok()) {
+        lines.push((Line::from(date), None));
+    }
+    for (i, SubTask { text, completed }) in task.checklist.iter().flatten().enumerate() {
+        let check = match (completed, cfg!(feature = "dark-mode")) {
+            (true, _) => "✅",
+            (false, true) => "⬛",
+            (false, false) => "⬜",
+        };
+        let mut spans = vec![Span::raw(format!("{check} "))];
+        spans.extend(render_inline(text, Style::default()));
+        let indent = display_width(check) + 1;
+        lines.extend(
+            wrap_line(Line::from(spans), width, indent)
+                .into_iter()
+                .map(|l| (l, Some(i))),
+        );
+    }
+
+    lines
+}
+
+/// Fit the lines of a task in the given height. Subtasks which do not fit are
+/// replaced by a line telling how many are hidden, and other text which is
+/// cut short ends in an ellipsis
+fn fit_lines(
+    mut lines: Vec<(Line<'static>, Option<usize>)>,
+    height: usize,
+    width: usize,
+) -> Vec<(Line<'static>, Option<usize>)> {
+    if lines.len() <= height || height == 0 {
+        lines.truncate(height);
+        return lines;
+    }
+
+    if let Some(first_hidden) = lines[height - 1].1 {
+        let n_subtasks = lines.iter().filter_map(|(_, sub)| *sub).max().unwrap_or(0) + 1;
+        // Subtasks which would be partly visible are hidden as a whole
+        lines.truncate(height - 1);
+        lines.retain(|(_, sub)| sub.is_none_or(|s| s < first_hidden));
+        let n_hidden = n_subtasks - first_hidden;
+        let plural = if n_hidden == 1 { "" } else { "s" };
+        let more = format!("+{n_hidden} more subtask{plural}");
+        lines.push((Line::from(more).italic(), None));
+    } else {
+        lines.truncate(height);
+        if let Some((last, _)) = lines.last_mut() {
+            let is_cut = line_width(last) >= width;
+            *last = truncate_line(mem::take(last), width.saturating_sub(1));
+            if !is_cut {
+                last.push_span("…");
+            }
+        }
+    }
+
+    lines
+}
+
+/// Compare optional values so that missing values come last
+fn cmp_none_last<T>(a: Option<T>, b: Option<T>, cmp: impl Fn(T, T) -> Ordering) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[derive(Default)]
 pub struct TaskGridState {
     pub page: usize,
     pub selected: Option<usize>,
     pub selected_sub: Option<usize>,
     pub task_items: Vec<Task>,
+    #[allow(dead_code)]
     pub loading: bool,
     pub modifications: HashMap<Uuid, Diff>,
     pub mod_key: Option<(KeyEvent, u32)>,
+    pub view: GridView,
+    pub grouped: bool,
+    /// Count typed before a motion, e.g. 3 in 3j
+    pub count: Option<usize>,
+    pub layout: GridLayout,
+    /// Tasks picked one by one for bulk actions
+    pub marked: HashSet<Uuid>,
+    /// Index where the range picked in visual mode starts
+    pub visual_anchor: Option<usize>,
+    /// Names of the tags added to tasks which are not submitted yet, by the
+    /// placeholder ID standing in for the tag until then
+    pub new_tags: HashMap<Uuid, String>,
+    /// Order of the tasks in Habitica, which the manual reorders are planned
+    /// against when they are submitted
+    pub server_order: Vec<Uuid>,
+    table_state: TableState,
+    // Number of rows shown in the table layout
+    table_height: usize,
 }
 
-impl Default for TaskGridState {
-    fn default() -> Self {
-        Self {
-            page: 0,
-            selected: None,
-            selected_sub: None,
-            task_items: Vec::new(),
-            modifications: HashMap::new(),
-            loading: false,
-            mod_key: None,
-        }
-    }
-}
 impl TaskGridState {
     pub fn select_first(&mut self) {
         self.selected_sub = None;
@@ -62,36 +223,47 @@ impl TaskGridState {
         self.page = len / GRID_SIZE;
     }
 
+    /// Select the task at an index of the grid, or the last task if there
+    /// are fewer tasks
+    pub fn select_index(&mut self, index: usize) {
+        let Some(last) = self.task_items.len().checked_sub(1) else {
+            return;
+        };
+        let index = index.min(last);
+        self.selected_sub = None;
+        self.selected = Some(index);
+        self.page = index / GRID_SIZE;
+    }
+
     pub fn select_next(&mut self, direction: Direction) {
         self.selected_sub = None;
 
-        if let None = self.selected {
+        if self.selected.is_none() {
             self.selected = Some(self.page * GRID_SIZE);
             return;
         }
 
         let mut selection = self.selected.unwrap() as i32;
-        let w = GRID_WIDTH as i32;
+        // Rows of the table are a single task wide
+        let w = match self.layout {
+            GridLayout::Cards => GRID_WIDTH as i32,
+            GridLayout::Table => 1,
+        };
         let h = GRID_HEIGHT as i32;
 
         selection = match direction {
             Direction::UP => selection - w,
             Direction::DOWN => selection + w,
-            Direction::LEFT => selection - 1,
-            Direction::RIGHT => selection + 1,
+            Direction::LEFT if self.layout == GridLayout::Cards => selection - 1,
+            Direction::RIGHT if self.layout == GridLayout::Cards => selection + 1,
+            _ => selection,
         };
 
         // Clamp selection between 0 and items.len
         selection = selection.clamp(0, max(0, self.task_items.len() as i32 - 1));
 
-        // Selection is on previous page
-        if selection < (self.page as i32) * w * h {
-            self.page -= 1;
-        }
-        // Selection is on next page
-        else if selection >= ((self.page as i32) + 1) * w * h {
-            self.page += 1;
-        }
+        // Keep the page of the cards on the selection
+        self.page = selection as usize / (GRID_WIDTH * h as usize);
 
         match usize::try_from(selection) {
             Ok(s) => self.selected = Some(s),
@@ -99,16 +271,16 @@ impl TaskGridState {
         }
     }
 
-    fn get_all_items(&self) -> Vec<&Task> {
+    /// Tasks in server order with pending edits applied
+    fn get_modified_items(&self) -> Vec<&Task> {
         self.task_items
             .iter()
             .map(|t| {
                 self.modifications.get(&t.id).map_or(t, |mods| {
                     let mut task = t;
                     for m in mods {
-                        match m {
-                            Action::Edit(m_task) => task = m_task,
-                            _ => {}
+                        if let Action::Edit(m_task) = m {
+                            task = m_task
                         }
                     }
                     task
@@ -117,6 +289,128 @@ impl TaskGridState {
             .collect()
     }
 
+    /// Tasks in the order of the current view. Sorting is stable, so tasks
+    /// which compare equal keep their server order
+    fn get_all_items(&self) -> Vec<&Task> {
+        let items = self.get_modified_items();
+        let mut order: Vec<usize> = (0..items.len()).collect();
+
+        match self.view {
+            GridView::Server => {}
+            GridView::Priority => order = priority_order(&items),
+            GridView::Due => order
+                .sort_by(|&a, &b| cmp_none_last(items[a].date, items[b].date, |a, b| a.cmp(&b))),
+            GridView::Difficulty => order.sort_by(|&a, &b| {
+                let (a, b): (f64, f64) = (items[a].difficulty.into(), items[b].difficulty.into());
+                b.total_cmp(&a)
+            }),
+            GridView::Checklist => order.sort_by(|&a, &b| {
+                cmp_none_last(
+                    checklist_progress(items[a]),
+                    checklist_progress(items[b]),
+                    |a, b| b.total_cmp(&a),
+                )
+            }),
+        }
+
+        order.into_iter().map(|i| items[i]).collect()
+    }
+
+    /// Section header of the group the task belongs to in the current view,
+    /// None if the view is not grouped
+    fn get_group_label(&self, task: &Task) -> Option<String> {
+        if !self.grouped {
+            return None;
+        }
+
+        match self.view {
+            GridView::Server => None,
+            GridView::Priority => Some(task.get_priority().to_string()),
+            GridView::Difficulty => Some(task.difficulty.to_string()),
+            GridView::Due => {
+                let label = match task.date {
+                    None => "No due date",
+                    Some(due) => {
+                        let today = OffsetDateTime::now_utc().date();
+                        match due.date() {
+                            d if d < today => "Overdue",
+                            d if d == today => "Today",
+                            d if d - today <= Duration::days(7) => "This week",
+                            _ => "Later",
+                        }
+                    }
+                };
+                Some(label.to_string())
+            }
+            GridView::Checklist => {
+                let label = match checklist_progress(task) {
+                    None => "No checklist",
+                    Some(p) if p >= 1.0 => "Done",
+                    Some(p) if p > 0.0 => "In progress",
+                    Some(_) => "Not started",
+                };
+                Some(label.to_string())
+            }
+        }
+    }
+
+    /// Switch to the given view keeping the selected task selected
+    fn set_view(&mut self, view: GridView, grouped: bool) {
+        let selected_id = self.get_selected().map(|t| t.id);
+        self.view = view;
+        self.grouped = grouped && view != GridView::Server;
+        self.selected_sub = None;
+
+        if let Some(id) = selected_id {
+            let index = self.get_all_items().iter().position(|t| t.id == id);
+            self.selected = index;
+            self.page = index.map_or(0, |i| i / GRID_SIZE);
+        }
+    }
+
+    pub fn next_view(&mut self) {
+        self.set_view(self.view.next(), self.grouped);
+    }
+
+    pub fn toggle_grouped(&mut self) {
+        self.set_view(self.view, !self.grouped);
+    }
+
+    /// Make the order of the current view the server order. Returns the moves
+    /// needed to reorder the tasks in Habitica, or None if there is nothing to
+    /// commit. Committing is refused while manual reorders are pending, as
+    /// those are relative to the server order they were made in
+    pub fn commit_view(&mut self) -> Option<Vec<(Uuid, usize)>> {
+        let has_pending_reorder = self
+            .modifications
+            .values()
+            .any(|diff| diff.iter().any(|m| matches!(m, Action::Reorder(_))));
+        if self.view == GridView::Server || has_pending_reorder {
+            return None;
+        }
+
+        let current: Vec<Uuid> = self.task_items.iter().map(|t| t.id).collect();
+        let desired: Vec<Uuid> = self.get_all_items().iter().map(|t| t.id).collect();
+        let moves = plan_moves(&current, &desired);
+
+        self.task_items
+            .sort_by_key(|t| desired.iter().position(|id| *id == t.id));
+        self.server_order = desired;
+        // The selection index stays valid as the view order is now the server
+        // order
+        self.view = GridView::Server;
+        self.grouped = false;
+
+        Some(moves)
+    }
+
+    /// Moves turning the order in Habitica into the order left by the manual
+    /// reorders
+    pub fn pending_moves(&self) -> Vec<(Uuid, usize)> {
+        let local: Vec<Uuid> = self.task_items.iter().map(|t| t.id).collect();
+        plan_moves(&self.server_order, &local)
+    }
+
     pub fn select_next_sub(&mut self) {
         let Some(checklist) = self.get_selected_checklist() else {
             return;
@@ -143,7 +437,12 @@ impl TaskGridState {
         }
     }
 
+    /// Move the selected task. Only possible in the server view, since in the
+    /// sorted views the position of a task is decided by the sort
     pub fn move_task(&mut self, direction: Direction) {
+        if self.view != GridView::Server {
+            return;
+        }
         let Some(selected) = self.selected else {
             return;
         };
@@ -152,15 +451,22 @@ impl TaskGridState {
         let next_selected = self.selected.unwrap();
         let task = self.task_items.remove(selected);
 
-        self.upsert_modified(task.id.clone(), Action::Reorder((selected, next_selected)));
+        self.upsert_modified(task.id, Action::Reorder((selected, next_selected)));
         self.task_items.insert(next_selected, task);
     }
 
     pub fn mark_item_completed(&mut self) {
+        if self.has_multi_select() {
+            for id in self.get_targets() {
+                self.upsert_modified(id, Action::ToggleComplete);
+            }
+            self.clear_multi_select();
+            return;
+        }
         let Some(mut task) = self.get_selected().cloned() else {
             return;
         };
-        let id = task.id.clone();
+        let id = task.id;
         if let Some(selected_sub) = self.selected_sub {
             let Some(checklist) = task.checklist.as_mut() else {
                 return;
@@ -186,15 +492,196 @@ impl TaskGridState {
         }
     }
 
+    /// Mark a task, or its subtask with the given text, as completed unless
+    /// it already is. The subtask is looked up by its text, since the
+    /// checklist may have changed since it was picked, and skipped if it no
+    /// longer exists. Unlike mark_item_completed the task does not need to be
+    /// selected
+    pub fn complete_item(&mut self, id: Uuid, sub: Option<&str>) {
+        let Some(task) = self.task_items.iter().find(|t| t.id == id) else {
+            return;
+        };
+        match sub {
+            Some(text) => self.edit_targets(&[id], |task| {
+                let mut checklist = task.checklist.iter_mut().flatten();
+                if let Some(subtask) = checklist.find(|s| s.text == text) {
+                    subtask.completed = true;
+                }
+            }),
+            None if !self.with_modifications(task).1 => {
+                self.upsert_modified(id, Action::ToggleComplete)
+            }
+            None => {}
+        }
+    }
+
     pub fn mark_item_removed(&mut self) {
-        let Some(task) = self.get_selected().cloned() else {
+        for id in self.get_targets() {
+            self.upsert_modified(id, Action::Remove);
+        }
+        self.clear_multi_select();
+    }
+
+    /// Start picking a range of tasks, or stop if already picking one
+    pub fn toggle_visual(&mut self) {
+        if self.visual_anchor.is_some() {
+            self.visual_anchor = None;
+            return;
+        }
+        if self.selected.is_none() {
+            self.select_next(Direction::DOWN);
+        }
+        self.visual_anchor = self.selected;
+    }
+
+    /// Pick the selected task for bulk actions, or unpick it
+    pub fn toggle_marked(&mut self) {
+        let Some(id) = self.get_selected().map(|t| t.id) else {
             return;
         };
-        self.upsert_modified(task.id, Action::Remove);
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+    }
+
+    pub fn has_multi_select(&self) -> bool {
+        self.visual_anchor.is_some() || !self.marked.is_empty()
+    }
+
+    pub fn clear_multi_select(&mut self) {
+        self.visual_anchor = None;
+        self.marked.clear();
+    }
+
+    /// Whether the task at an index of the view is picked for bulk actions
+    fn is_marked(&self, index: usize, task: &Task) -> bool {
+        let in_range = match (self.visual_anchor, self.selected) {
+            (Some(anchor), Some(selected)) => {
+                (anchor.min(selected)..=anchor.max(selected)).contains(&index)
+            }
+            _ => false,
+        };
+        in_range || self.marked.contains(&task.id)
+    }
+
+    /// IDs of the tasks which actions apply to in the order of the view. These
+    /// are the picked tasks if there are any, and the selected task otherwise
+    pub fn get_targets(&self) -> Vec<Uuid> {
+        if !self.has_multi_select() {
+            return self.get_selected().map(|t| t.id).into_iter().collect();
+        }
+        self.get_all_items()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, t)| self.is_marked(*i, t))
+            .map(|(_, t)| t.id)
+            .collect()
+    }
+
+    /// Record an edit of a task, dropping it if the task is back to how it
+    /// was originally
+    fn upsert_edit(&mut self, task: Task) {
+        let id = task.id;
+        if self.task_items.contains(&task) {
+            if let Some(diff) = self.modifications.get_mut(&id) {
+                diff.remove(&Action::Edit(task));
+                if diff.is_empty() {
+                    self.modifications.remove(&id);
+                }
+            }
+        } else {
+            self.modifications
+                .entry(id)
+                .or_default()
+                .replace(Action::Edit(task));
+        }
+    }
+
+    /// Edit each of the targeted tasks, starting from their pending edits
+    fn edit_targets(&mut self, ids: &[Uuid], edit: impl Fn(&mut Task)) {
+        for id in ids {
+            let Some(task) = self.task_items.iter().find(|t| t.id == *id) else {
+                continue;
+            };
+            let mut task = self.with_modifications(task).0.clone();
+            edit(&mut task);
+            self.upsert_edit(task);
+        }
+    }
+
+    pub fn change_difficulty(&mut self, harder: bool) {
+        let targets = self.get_targets();
+        self.edit_targets(&targets, |task| {
+            task.difficulty = if harder {
+                task.difficulty.next()
+            } else {
+                task.difficulty.prev()
+            };
+        });
+    }
+
+    /// Move the due dates of the targeted tasks by a number of days. Tasks
+    /// without a due date are left as they are
+    pub fn shift_due_dates(&mut self, days: i64) {
+        let targets = self.get_targets();
+        self.edit_targets(&targets, |task| {
+            task.date = task.date.map(|d| d + Duration::days(days));
+        });
+    }
+
+    /// Add a tag by name to the targeted tasks. The tag is only looked up,
+    /// or created if it does not exist yet, once the changes are submitted
+    pub fn add_tag(&mut self, ids: &[Uuid], name: String) {
+        let placeholder = self
+            .new_tags
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(&name))
+            .map(|(id, _)| *id);
+        let tag = placeholder.unwrap_or_else(|| {
+            let id = Uuid::now_v7();
+            self.new_tags.insert(id, name);
+            id
+        });
+        self.edit_targets(ids, |task| {
+            if !task.tags.contains(&tag) {
+                task.tags.push(tag);
+            }
+        });
+    }
+
+    /// Move the targeted tasks to the top keeping their order. Only possible
+    /// in the server view, like moving a single task
+    pub fn move_to_top(&mut self) {
+        if self.view != GridView::Server {
+            return;
+        }
+        let targets = self.get_targets();
+        for (to, id) in targets.iter().enumerate() {
+            let Some(from) = self.task_items.iter().position(|t| t.id == *id) else {
+                continue;
+            };
+            let task = self.task_items.remove(from);
+            self.task_items.insert(to, task);
+            self.upsert_modified(*id, Action::Reorder((from, to)));
+        }
+        self.clear_multi_select();
+        self.select_first();
+    }
+
+    pub fn toggle_layout(&mut self) {
+        self.layout = match self.layout {
+            GridLayout::Cards => GridLayout::Table,
+            GridLayout::Table => GridLayout::Cards,
+        };
     }
 
     pub fn next_page(&mut self) {
-        if self.task_items.len() > ((self.page + 1) * GRID_SIZE).into() {
+        if self.layout == GridLayout::Table {
+            let selected = self.selected.unwrap_or(0);
+            self.select_index(selected + self.table_height.max(1));
+            return;
+        }
+        if self.task_items.len() > ((self.page + 1) * GRID_SIZE) {
             self.selected_sub = None;
             self.selected = self.selected.map(|s| s + GRID_SIZE);
 
@@ -203,6 +690,11 @@ impl TaskGridState {
     }
 
     pub fn prev_page(&mut self) {
+        if self.layout == GridLayout::Table {
+            let selected = self.selected.unwrap_or(0);
+            self.select_index(selected.saturating_sub(self.table_height.max(1)));
+            return;
+        }
         if self.page != 0 {
             self.selected_sub = None;
             self.selected = self.selected.map(|s| s - GRID_SIZE);
@@ -235,11 +727,9 @@ impl TaskGridState {
                         }
                         return;
                     }
-                    (_, Some(Action::Edit(t))) => {
-                        if self.task_items.contains(t) {
-                            diff.remove(&modification);
-                            return;
-                        }
+                    (_, Some(Action::Edit(t))) if self.task_items.contains(t) => {
+                        diff.remove(&modification);
+                        return;
                     }
                     _ => {}
                 }
@@ -257,21 +747,45 @@ impl TaskGridState {
         self.selected.map(|i| *self.get_all_items().get(i).unwrap())
     }
 
+    /// Background of a task showing whether it is selected or modified
+    fn item_style(&self, index: usize, task: &Task) -> Style {
+        let mods = self.modifications.get(&task.id);
+        let is_selected = Some(index) == self.selected;
+        let is_modified = mods.is_some();
+        let is_remove = mods.is_some_and(|set| set.contains(&Action::Remove));
+
+        match (is_selected, is_modified, is_remove) {
+            (true, _, _) => Style::default().bg(Palette::GREEN.into()),
+            _ if self.is_marked(index, task) => Style::default().bg(Palette::GREEN2.into()),
+            (_, _, true) => Style::default().bg(Palette::RED.into()),
+            (_, true, _) => Style::default().bg(Palette::YELLOW.into()),
+            _ => Style::default().bg(Palette::BG2.into()),
+        }
+    }
+
+    /// Task as it is after its pending edits, and whether it is marked
+    /// completed
+    pub fn with_modifications<'a>(&'a self, task: &'a Task) -> (&'a Task, bool) {
+        let mut rendered = task;
+        let mut completed = false;
+        for m in self.modifications.get(&task.id).into_iter().flatten() {
+            match m {
+                Action::Edit(m_task) => rendered = m_task,
+                Action::ToggleComplete => completed = true,
+                _ => {}
+            }
+        }
+        (rendered, completed)
+    }
+
     fn get_selected_checklist(&self) -> Option<&Vec<SubTask>> {
         self.get_selected()
             .and_then(|task| task.checklist.as_ref().filter(|l| !l.is_empty()))
     }
 
-    fn get_line_offset_selected_sub(&self) -> Option<usize> {
-        self.selected_sub.map(|index| {
-            let mut i = index + 1;
-            let task = self.get_selected();
-            task.map(|t| {
-                t.notes.clone().map(|_| i += 1);
-                t.date.map(|_| i += 1);
-            });
-            i
-        })
+    pub fn push_count_digit(&mut self, digit: char) {
+        let digit = digit.to_digit(10).unwrap_or(0) as usize;
+        self.count = Some(self.count.unwrap_or(0).saturating_mul(10) + digit);
     }
 
     pub fn add_mod_key(&mut self, key: KeyEvent) {
@@ -284,7 +798,7 @@ impl TaskGridState {
 
     // Decay mod key press TTL
     pub fn decay_mod_key(&mut self) {
-        if let Some(mut mk) = self.mod_key.clone() {
+        if let Some(mut mk) = self.mod_key {
             mk.1 -= 1;
             if mk.1 == 0 {
                 self.mod_key = None;
@@ -301,6 +815,11 @@ pub struct TaskGrid {}
 impl StatefulWidget for TaskGrid {
     type State = TaskGridState;
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut TaskGridState) {
+        if state.layout == GridLayout::Table {
+            render_table(area, buf, state);
+            return;
+        }
+
         let cols: Vec<[Rect; GRID_HEIGHT]> = Layout::horizontal([
             Constraint::Fill(1),
             Constraint::Fill(1),
@@ -319,6 +838,8 @@ impl StatefulWidget for TaskGrid {
         })
         .collect();
 
+        let items = state.get_all_items();
+
         for j in 0..GRID_HEIGHT {
             for i in 0..GRID_WIDTH {
                 let col = cols.get(i).unwrap();
@@ -327,70 +848,151 @@ impl StatefulWidget for TaskGrid {
 
                 let index = state.page * GRID_WIDTH * GRID_HEIGHT + j * GRID_WIDTH + i;
 
-                if let Some(task) = state.get_all_items().get(index).as_deref() {
-                    let mod_task_opt = state.modifications.get(&task.id);
+                if let Some(task) = items.get(index) {
                     let is_selected = Some(index) == state.selected;
-                    let is_modified = mod_task_opt.is_some();
-                    let is_remove = mod_task_opt.is_some_and(|set| set.contains(&Action::Remove));
-
-                    let style = match (is_selected, is_modified, is_remove) {
-                        (true, _, _) => Style::default().bg(Palette::GREEN.into()),
-                        (_, _, true) => Style::default().bg(Palette::RED.into()),
-                        (_, true, _) => Style::default().bg(Palette::YELLOW.into()),
-                        _ => Style::default().bg(Palette::BG2.into()),
-                    };
-
-                    let (rendered_task, completed) = mod_task_opt.map_or((*task, false), |set| {
-                        let mut t = *task;
-                        let mut completed = false;
-                        for m in set.iter() {
-                            match m {
-                                Action::Edit(m_task) => t = m_task,
-                                Action::ToggleComplete => completed = true,
-                                _ => {}
-                            }
-                        }
-                        (t, completed)
-                    });
+                    let style = state.item_style(index, task);
 
-                    let block = Block::default()
+                    let (rendered_task, completed) = state.with_modifications(task);
+
+                    let mut block = Block::default()
                         .padding(Padding::proportional(1))
                         .style(style);
 
+                    if let Some(color) = priority_color(rendered_task.get_priority()) {
+                        block = block
+                            .borders(Borders::LEFT)
+                            .border_type(BorderType::Thick)
+                            .border_style(Style::default().fg(color));
+                    }
+
                     let inner = block.inner(cell);
-                    let max_y = inner.y + inner.height;
                     block.render(cell, buf);
 
-                    for (i, line_str) in rendered_task.to_string().split("\n").enumerate() {
-                        let mut line_style = style;
+                    // Header on the first task of each group and on the first
+                    // task of the page
+                    let group_label = state.get_group_label(task);
+                    let starts_group = index.is_multiple_of(GRID_SIZE)
+                        || group_label != state.get_group_label(items[index - 1]);
+                    if let Some(label) = group_label.filter(|_| starts_group && inner.y > cell.y) {
+                        Paragraph::new(label).style(style.bold()).render(
+                            Rect {
+                                x: inner.x,
+                                y: cell.y,
+                                width: inner.width,
+                                height: 1,
+                            },
+                            buf,
+                        );
+                    }
+
+                    let width = inner.width.saturating_sub(2);
+                    let lines = fit_lines(
+                        task_lines(rendered_task, completed, width as usize),
+                        inner.height as usize,
+                        width as usize,
+                    );
+                    for (i, (line, subtask)) in lines.into_iter().enumerate() {
                         let y = inner.y + i as u16;
-                        if let Some(subtask_i) = state.get_line_offset_selected_sub() {
-                            if is_selected && i == subtask_i {
-                                line_style = Style::default().bg(Palette::GREEN2.into());
+                        let line_style = match subtask {
+                            Some(sub) if is_selected && state.selected_sub == Some(sub) => {
+                                Style::default().bg(Palette::GREEN2.into())
                             }
-                        }
-                        if y < max_y {
-                            Paragraph::new(line_str).style(line_style).render(
-                                Rect {
-                                    x: inner.x,
-                                    y,
-                                    width: inner.width - 2,
-                                    height: 1,
-                                },
-                                buf,
-                            );
-                        }
-                        if i == 0 && completed {
-                            buf.set_string(
-                                inner.x + rendered_task.text.len() as u16 + 1,
+                            _ => style,
+                        };
+                        Paragraph::new(line).style(line_style).render(
+                            Rect {
+                                x: inner.x,
                                 y,
-                                "✅",
-                                line_style,
-                            );
-                        }
+                                width,
+                                height: 1,
+                            },
+                            buf,
+                        );
                     }
                 }
             }
         }
     }
 }
+
+/// Render the tasks as a table with a row per task. With grouping on, each
+/// group starts with a row for its label
+fn render_table(area: Rect, buf: &mut Buffer, state: &mut TaskGridState) {
+    let area = area.inner(Margin::new(1, 0));
+    let header = Row::new(["", "", "Task", "Difficulty", "Due", "Checklist"])
+        .style(Style::default().bold())
+        .bottom_margin(1);
+    let widths = [
+        Constraint::Length(1),
+        Constraint::Length(2),
+        Constraint::Fill(1),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(9),
+    ];
+    // Width of the task column, taking the spacing of the columns into account
+    let fixed: u16 = 1 + 2 + 10 + 10 + 9 + 5;
+    let title_width = area.width.saturating_sub(fixed) as usize;
+
+    let items = state.get_all_items();
+    let mut rows = Vec::new();
+    let mut selected_row = None;
+    let mut prev_label = None;
+    for (index, task) in items.iter().enumerate() {
+        let group_label = state.get_group_label(task);
+        if group_label.is_some() && group_label != prev_label {
+            let label = group_label.clone().unwrap_or_default();
+            rows.push(
+                Row::new(["".to_string(), "".to_string(), label]).style(Style::default().bold()),
+            );
+        }
+        prev_label = group_label;
+
+        if Some(index) == state.selected {
+            selected_row = Some(rows.len());
+        }
+        let (task, completed) = state.with_modifications(task);
+        let priority = match priority_color(task.get_priority()) {
+            Some(color) => Span::styled("┃", Style::default().fg(color)),
+            None => Span::raw(""),
+        };
+        let check = match (completed, cfg!(feature = "dark-mode")) {
+            (true, _) => "✅",
+            (false, true) => "⬛",
+            (false, false) => "⬜",
+        };
+        let title = truncate_line(
+            Line::from(render_inline(&task.text, Style::default())),
+            title_width,
+        );
+        let due = task
+            .date
+            .and_then(|d| d.format(&Iso8601::DATE).ok())
+            .unwrap_or_default();
+        let progress = task
+            .checklist
+            .as_ref()
+            .filter(|c| !c.is_empty())
+            .map(|c| format!("{}/{}", c.iter().filter(|s| s.completed).count(), c.len()))
+            .unwrap_or_default();
+
+        let cells: [Cell; 6] = [
+            Cell::from(priority),
+            Cell::from(check),
+            Cell::from(title),
+            Cell::from(task.difficulty.to_string()),
+            Cell::from(due),
+            Cell::from(progress),
+        ];
+        rows.push(Row::new(cells).style(state.item_style(index, task)));
+    }
+
+    // Rows without the header and its margin
+    state.table_height = area.height.saturating_sub(2) as usize;
+    state.table_state.select(selected_row);
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .style(Style::default().bg(Palette::BG2.into()));
+    StatefulWidget::render(table, area, buf, &mut state.table_state);
+}
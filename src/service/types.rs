@@ -1,6 +1,7 @@
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+use clap::ValueEnum;
 use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
@@ -10,9 +11,11 @@ use sqlx::{
     types::{time::OffsetDateTime, uuid::Uuid},
 };
 use time::format_description::well_known::Iso8601;
-use time::Duration;
 
-#[derive(Debug, PartialEq, Copy, Clone, Type)]
+use super::{config::get_config, priority::PriorityRule, width::pad_to_width};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Copy, Clone, Type, ValueEnum)]
 pub enum Difficulty {
     TRIVIAL,
     EASY,
@@ -20,12 +23,10 @@ pub enum Difficulty {
     HARD,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub enum Priority {
-    LOW,
-    MID,
-    HIGH,
-}
+/// Rank of a priority level configured in PriorityConfig, where a higher
+/// rank means a higher priority
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub struct Priority(pub usize);
 
 impl fmt::Display for Difficulty {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -41,9 +42,9 @@ impl fmt::Display for Difficulty {
     }
 }
 
-impl Into<f64> for Difficulty {
-    fn into(self) -> f64 {
-        match self {
+impl From<Difficulty> for f64 {
+    fn from(val: Difficulty) -> Self {
+        match val {
             Difficulty::TRIVIAL => 0.1,
             Difficulty::EASY => 1.0,
             Difficulty::MEDIUM => 1.5,
@@ -136,7 +137,7 @@ impl<'de> Deserialize<'de> for Difficulty {
     }
 }
 
-fn skip_serialize_in_prod(_id: &Uuid) -> bool {
+fn skip_serialize_in_prod<T>(_value: &T) -> bool {
     cfg!(not(debug_assertions))
 }
 
@@ -169,7 +170,55 @@ pub struct Task {
         serialize_with = "time::serde::iso8601::option::serialize"
     )]
     pub completed_at: Option<OffsetDateTime>,
+    #[serde(
+        rename = "createdAt",
+        default,
+        skip_serializing_if = "skip_serialize_in_prod",
+        deserialize_with = "time::serde::iso8601::option::deserialize",
+        serialize_with = "time::serde::iso8601::option::serialize"
+    )]
+    #[sqlx(default)]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(
+        rename = "updatedAt",
+        default,
+        skip_serializing_if = "skip_serialize_in_prod",
+        deserialize_with = "time::serde::iso8601::option::deserialize",
+        serialize_with = "time::serde::iso8601::option::serialize"
+    )]
+    #[sqlx(default)]
+    pub updated_at: Option<OffsetDateTime>,
     pub checklist: Option<Vec<SubTask>>,
+    #[serde(default)]
+    #[sqlx(default)]
+    pub tags: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Tag {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Daily as returned by the Habitica API. Only the fields needed to tell
+/// whether the daily is still to be done today are read
+#[derive(Debug, Deserialize, Clone)]
+pub struct Daily {
+    pub completed: bool,
+    #[serde(rename = "isDue")]
+    pub is_due: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserStats {
+    pub hp: f64,
+    #[serde(rename = "maxHealth")]
+    pub max_health: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub stats: UserStats,
 }
 
 impl Default for Task {
@@ -182,18 +231,20 @@ impl Default for Task {
             difficulty: Difficulty::EASY,
             date: None,
             completed_at: None,
+            created_at: None,
+            updated_at: None,
             checklist: None,
+            tags: Vec::new(),
         }
     }
 }
 
 impl fmt::Display for Task {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:48}{:7}", &self.text, &self.difficulty)?;
+        write!(f, "{}{:7}", pad_to_width(&self.text, 48), &self.difficulty)?;
         let _ = &self.notes.clone().map(|n| write!(f, "\n{}", n));
         let _ = &self
             .date
-            .clone()
             .map(|d| write!(f, "\n{}", d.format(&Iso8601::DATE).unwrap()));
 
         if let Some(subtasks) = &self.checklist {
@@ -210,31 +261,19 @@ impl fmt::Display for Task {
                 write!(f, "\n{check} {text}")?;
             }
         }
-        write!(f, "\n")
+        writeln!(f)
     }
 }
 
 impl Task {
     pub fn get_priority(&self) -> Priority {
-        if self.notes.as_ref().is_some_and(|n| n.contains("🎓")) {
-            return Priority::MID;
-        }
-
-        // Due date is within the next week
-        let is_expiring = self.date.is_some_and(|d| {
-            OffsetDateTime::now_utc()
-                .checked_add(Duration::weeks(1))
-                .is_some_and(|wk| d < wk)
-        });
-
-        // 🔥 to communicate priority in other cases
-        let is_fire = self.notes.as_ref().is_some_and(|n| n.contains("🔥"));
-
-        if is_expiring || is_fire {
-            return Priority::HIGH;
-        };
+        get_config().priority.evaluate(self).0
+    }
 
-        return Priority::LOW;
+    /// Get the priority of the task along with the rule which assigned it.
+    /// No rule is returned if the task has the default priority
+    pub fn explain_priority(&self) -> (Priority, Option<&'static PriorityRule>) {
+        get_config().priority.evaluate(self)
     }
 }
 
@@ -250,14 +289,14 @@ pub enum Action {
 impl PartialEq for Action {
     fn eq(&self, other: &Self) -> bool {
         use Action::*;
-        match (self, other) {
-            (ToggleComplete, ToggleComplete) => true,
-            (Edit(_), Edit(_)) => true,
-            (Reorder(_), Reorder(_)) => true,
-            (Remove, Remove) => true,
-            (Create, Create) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (ToggleComplete, ToggleComplete)
+                | (Edit(_), Edit(_))
+                | (Reorder(_), Reorder(_))
+                | (Remove, Remove)
+                | (Create, Create)
+        )
     }
 }
 
@@ -273,7 +312,6 @@ impl Hash for Action {
             Remove => state.write(&[3]),
             Create => state.write(&[4]),
         };
-        state.finish();
     }
 }
 
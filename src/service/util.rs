@@ -1,21 +1,42 @@
 use std::env;
-use std::fs::File;
-use std::io::Write;
-
-use inquire::{max_length, min_length, DateSelect, Select, Text};
-use log::debug;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use inquire::{max_length, min_length, validator::Validation, DateSelect, Select, Text};
+use log::{debug, info};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use sqlx::{types::uuid::Uuid, PgPool, Postgres};
-use time::{format_description::well_known::Iso8601, OffsetDateTime};
+use sqlx::{types::uuid::Uuid, PgPool, Postgres, QueryBuilder};
+use time::{format_description::well_known::Iso8601, Date, Duration, OffsetDateTime};
+use tokio::time::{sleep, Instant};
 
 use super::{
-    request::{fetch_tasks, post_created_task, reorder_task},
-    types::{Difficulty, Priority, SubTask, Task},
+    config::get_config,
+    date::{local_today, parse_natural_date},
+    descriptor::{parse_task_descriptor, print_descriptor_error, TaskDescriptor},
+    output::{is_human, print_status, print_task, print_tasks},
+    request::{fetch_tags, fetch_tasks, post_created_tag, post_created_task},
+    types::{Difficulty, SubTask, Tag, Task},
+};
+use crate::{
+    error::AppError,
+    types::{HistoryFilter, HistorySort},
+    util::build_config_path,
 };
-use crate::{error::AppError, util::build_config_path};
 
 pub const ISO8601: Iso8601 = Iso8601::DEFAULT;
 
+// Tags of the user, which are needed to refer to the tags of tasks by name
+static KNOWN_TAGS: RwLock<Vec<Tag>> = RwLock::new(Vec::new());
+
+// Habitica allows 30 requests per minute, so bulk requests are sent in
+// batches that each take up at least a third of a minute
+const RATE_LIMIT_BATCH_SIZE: usize = 10;
+const RATE_LIMIT_BATCH_WINDOW: std::time::Duration = std::time::Duration::from_secs(20);
+
 #[derive(Serialize, Deserialize)]
 pub struct ArrayRes<T> {
     pub data: Vec<T>,
@@ -30,18 +51,20 @@ pub fn get_json_path() -> Result<String, AppError> {
     Ok(format!("{dir}/habitica_tasks.json"))
 }
 
-pub fn get_env_vars() -> Result<(String, String, String, String), AppError> {
+pub fn get_env_vars() -> Result<(String, String, String), AppError> {
     Ok((
         env::var("HABITICA_USER_ID")?,
         env::var("HABITICA_TOKEN")?,
         env::var("HABITICA_XCLIENT")?,
-        env::var("POSTGRES_URL")?,
     ))
 }
 
-pub fn assert_service_installed() -> Result<(), AppError> {
+pub fn assert_service_installed(uses_database: bool) -> Result<(), AppError> {
     // Test that env was loaded correctly
     get_env_vars()?;
+    if uses_database {
+        env::var("POSTGRES_URL")?;
+    }
 
     Ok(())
 }
@@ -58,54 +81,37 @@ pub async fn run_migrations() -> Result<(), AppError> {
     Ok(())
 }
 
-fn parse_difficulty(selected: &str) -> Result<Difficulty, AppError> {
-    let parsed: Difficulty = match selected {
-        "Trivial" => Difficulty::TRIVIAL,
-        "Easy" => Difficulty::EASY,
-        "Medium" => Difficulty::MEDIUM,
-        "Hard" => Difficulty::HARD,
+pub fn parse_difficulty(selected: &str) -> Result<Difficulty, AppError> {
+    let parsed: Difficulty = match selected.to_lowercase().as_str() {
+        "trivial" => Difficulty::TRIVIAL,
+        "easy" => Difficulty::EASY,
+        "medium" => Difficulty::MEDIUM,
+        "hard" => Difficulty::HARD,
         _ => Err(AppError::CmdError("Incorrect difficulty value".into()))?,
     };
 
     Ok(parsed)
 }
 
-fn parse_task_descriptor(descriptor: String) -> Result<Task, AppError> {
-    let mut parts = descriptor.split(",");
-    let parts = (
-        parts.next(),
-        parts.next(),
-        parts.next(),
-        parts.next(),
-        parts.next(),
-    );
-    match parts {
-        (Some(text), Some(difficulty), notes, date, check) => {
-            return Ok(Task {
-                id: Uuid::nil(),
-                text: text.into(),
-                task_type: "todo".into(),
-                difficulty: parse_difficulty(difficulty)?,
-                notes: notes.map(|n| n.into()),
-                date: date.map(|d| OffsetDateTime::parse(d.into(), &ISO8601).unwrap()),
-                completed_at: None,
-                checklist: check.map(|c| {
-                    c.split(";")
-                        .map(|i| SubTask {
-                            text: i.into(),
-                            completed: false,
-                        })
-                        .collect()
-                }),
-            });
-        }
-        (None, ..) => Err(AppError::CmdError(
-            "Incorrect input: <name> required".into(),
-        ))?,
-        (_, None, ..) => Err(AppError::CmdError(
-            "Incorrect input: <difficulty> required".into(),
-        ))?,
+/// Parse a due date given as a natural language date such as "tomorrow" or
+/// "+3d", as a plain YYYY-MM-DD date or as a full ISO8601 timestamp. Dates
+/// are resolved in the local timezone and stored at midnight like the dates
+/// picked from a calendar
+pub fn parse_due_date(input: &str) -> Result<OffsetDateTime, AppError> {
+    parse_due_date_from(input, local_today())
+}
+
+/// Parse a due date, resolving relative dates such as +3d from the given day
+pub fn parse_due_date_from(input: &str, today: Date) -> Result<OffsetDateTime, AppError> {
+    if let Some(date) = parse_natural_date(input, today) {
+        return Ok(date.midnight().assume_utc());
+    }
+    if let Ok(date) = Date::parse(input, &Iso8601::DATE) {
+        return Ok(date.midnight().assume_utc());
     }
+
+    OffsetDateTime::parse(input, &ISO8601)
+        .map_err(|_| AppError::CmdError(format!("Incorrect due date: {input}")))
 }
 
 fn checklist_item_formatter(i: &str) -> String {
@@ -123,13 +129,13 @@ fn prompt_for_checklist() -> Result<Option<Vec<SubTask>>, AppError> {
             .with_formatter(&checklist_item_formatter)
             .prompt_skippable()?;
 
-        if item.is_none() {
-            finished = true;
-        } else {
+        if let Some(text) = item {
             list.push(SubTask {
-                text: item.unwrap(),
+                text,
                 completed: false,
             })
+        } else {
+            finished = true;
         }
 
         i += 1;
@@ -160,10 +166,27 @@ fn prompt_for_task() -> Result<Task, AppError> {
         .with_validator(max_length!(60, "Notes must be at most 60 characters."))
         .prompt()?;
 
-    let date = DateSelect::new("Due date:")
-        .with_help_message("Press ESC to skip")
-        .prompt_skippable()?
-        .map(|d| OffsetDateTime::parse(&d.format("%F").to_string(), &ISO8601).unwrap());
+    let due = Text::new("Due date:")
+        .with_help_message("e.g. tomorrow, fri, +3d or 2024-12-24. Leave empty to pick from a calendar, ESC to skip")
+        .with_validator(|input: &str| {
+            if input.trim().is_empty() || parse_due_date(input).is_ok() {
+                Ok(Validation::Valid)
+            } else {
+                Ok(Validation::Invalid("Unrecognised date".into()))
+            }
+        })
+        .prompt_skippable()?;
+
+    let date = match due.as_deref().map(str::trim) {
+        None => None,
+        Some("") => DateSelect::new("Due date:")
+            .with_starting_date(chrono::Local::now().date_naive())
+            .with_help_message("Press ESC to skip")
+            .prompt_skippable()?
+            .map(|d| parse_due_date(&d.format("%F").to_string()))
+            .transpose()?,
+        Some(input) => Some(parse_due_date(input)?),
+    };
 
     let checklist = prompt_for_checklist()?;
 
@@ -175,13 +198,25 @@ fn prompt_for_task() -> Result<Task, AppError> {
         notes: if notes.is_empty() { None } else { Some(notes) },
         date,
         completed_at: None,
+        created_at: None,
+        updated_at: None,
         checklist,
+        tags: Vec::new(),
     })
 }
 
-async fn query_completed_tasks() -> Result<Vec<Task>, AppError> {
+/// Escape LIKE wildcards so that the search text is matched literally
+fn build_search_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+pub async fn query_completed_tasks(filter: &HistoryFilter) -> Result<Vec<Task>, AppError> {
     let pool = create_pg_pool().await?;
-    let tasks = sqlx::query_as::<_, Task>(
+    let mut query = QueryBuilder::<Postgres>::new(
         "SELECT
             id,
             text,
@@ -190,33 +225,329 @@ async fn query_completed_tasks() -> Result<Vec<Task>, AppError> {
             notes,
             date,
             completed_at,
-            checklist 
-        FROM completed_task;
-        ",
-    )
-    .fetch_all(&pool)
-    .await?;
+            checklist
+        FROM completed_task
+        WHERE TRUE",
+    );
+
+    if let Some(since) = filter.since {
+        query
+            .push(" AND completed_at >= ")
+            .push_bind(since.midnight().assume_utc());
+    }
+
+    if let Some(until) = filter.until {
+        // Include the whole day given as the upper bound
+        query
+            .push(" AND completed_at < ")
+            .push_bind(until.midnight().assume_utc() + Duration::DAY);
+    }
+
+    if !filter.difficulty.is_empty() {
+        query
+            .push(" AND difficulty = ANY(")
+            .push_bind(filter.difficulty.clone())
+            .push(")");
+    }
+
+    if let Some(search) = &filter.search {
+        let pattern = build_search_pattern(search);
+        query
+            .push(" AND (text ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR notes ILIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR EXISTS (SELECT 1 FROM UNNEST(checklist) AS c WHERE c.text ILIKE ")
+            .push_bind(pattern)
+            .push("))");
+    }
+
+    let (column, descending) = match filter.sort {
+        HistorySort::Completed => ("completed_at", true),
+        HistorySort::Due => ("date", false),
+        HistorySort::Difficulty => ("difficulty", true),
+        HistorySort::Name => ("text", false),
+    };
+    let direction = if descending != filter.reverse {
+        "DESC"
+    } else {
+        "ASC"
+    };
+    query.push(format!(" ORDER BY {column} {direction} NULLS LAST, id"));
+
+    if let Some(limit) = filter.limit {
+        query.push(" LIMIT ").push_bind(i64::from(limit));
+    }
+
+    let tasks = query.build_query_as::<Task>().fetch_all(&pool).await?;
 
     Ok(tasks)
 }
 
-pub async fn create_task(descriptor: Option<String>) -> Result<(), AppError> {
-    let task: Task;
-    if descriptor.is_some() {
-        task = parse_task_descriptor(descriptor.unwrap())?;
+/// Read descriptors from a file, or from stdin if the path is -. Returns the
+/// line numbers and descriptors of the non-empty, non-comment lines
+fn read_descriptors(file: &Path) -> Result<Vec<(usize, String)>, AppError> {
+    let content = if file.as_os_str() == "-" {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        buf
     } else {
-        task = prompt_for_task()?;
+        fs::read_to_string(file)?
+    };
+
+    Ok(content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| (n, line.to_string()))
+        .collect())
+}
+
+/// Create a task for each descriptor in a file. Every line is validated
+/// before anything is created, so an invalid line leaves Habitica untouched
+async fn create_tasks_from_file(file: &Path, dry_run: bool) -> Result<(), AppError> {
+    let lines = read_descriptors(file)?;
+
+    let mut parsed: Vec<(usize, TaskDescriptor)> = Vec::with_capacity(lines.len());
+    let mut n_invalid = 0;
+    for (n, descriptor) in &lines {
+        match parse_task_descriptor(descriptor) {
+            Ok(d) => parsed.push((*n, d)),
+            Err(e) => {
+                n_invalid += 1;
+                eprintln!("Line {n}:");
+                print_descriptor_error(descriptor, &e);
+            }
+        }
     }
-    debug!("Creating task: \n{task}");
 
-    let created = post_created_task(task).await?;
+    if n_invalid > 0 {
+        Err(AppError::CmdError(format!(
+            "{n_invalid} of {} lines are invalid, no tasks were created",
+            lines.len()
+        )))?;
+    }
 
-    println!("Created: \n{}", created);
+    if dry_run {
+        if is_human() {
+            for (_, descriptor) in &parsed {
+                println!("{descriptor}");
+            }
+        } else {
+            let tasks: Vec<Task> = parsed.iter().map(|(_, d)| d.task.clone()).collect();
+            print_tasks(&tasks)?;
+        }
+        print_status(&format!("Dry run: all {} lines are valid", parsed.len()));
+        return Ok(());
+    }
+
+    let mut known_tags = if parsed.iter().any(|(_, d)| !d.tags.is_empty()) {
+        fetch_tags().await?
+    } else {
+        Vec::new()
+    };
+    let mut limiter = RateLimiter::default();
+    let mut line_numbers = Vec::with_capacity(parsed.len());
+    let mut tasks = Vec::with_capacity(parsed.len());
+    for (n, TaskDescriptor { mut task, tags }) in parsed {
+        task.tags = resolve_tag_ids(&tags, &mut known_tags, &mut limiter).await?;
+        line_numbers.push(n);
+        tasks.push(task);
+    }
+
+    let results = post_created_tasks(tasks, &mut limiter).await;
+
+    let mut created_tasks: Vec<Task> = Vec::new();
+    let mut failed: Vec<usize> = Vec::new();
+    for (n, res) in line_numbers.iter().zip(results) {
+        match res {
+            Ok(created) => {
+                debug!("Created task from line {n}: \n{created}");
+                created_tasks.push(created);
+            }
+            Err(e) => {
+                eprintln!("Failed to create task from line {n}: {e}");
+                failed.push(*n);
+            }
+        }
+    }
+
+    if !is_human() {
+        print_tasks(&created_tasks)?;
+    }
+    print_status(&format!(
+        "Created {} tasks, {} failed",
+        created_tasks.len(),
+        failed.len()
+    ));
+    if !failed.is_empty() {
+        let failed: Vec<String> = failed.iter().map(|n| n.to_string()).collect();
+        print_status(&format!("Failed lines: {}", failed.join(", ")));
+    }
 
     Ok(())
 }
 
+/// Create a task from a descriptor, from a file of descriptors, from a
+/// template or from the answers to prompts if none is given
+pub async fn create_task(
+    descriptor: Option<String>,
+    file: Option<PathBuf>,
+    template: Option<String>,
+    vars: Vec<(String, String)>,
+    dry_run: bool,
+) -> Result<(), AppError> {
+    let file = file.or_else(|| descriptor.as_ref().filter(|d| *d == "-").map(PathBuf::from));
+    if let Some(file) = file {
+        return create_tasks_from_file(&file, dry_run).await;
+    }
+
+    let descriptor = if let Some(template) = template {
+        get_config()
+            .templates
+            .get(&template)?
+            .instantiate(&vars, false)?
+    } else if let Some(descriptor) = descriptor {
+        parse_task_descriptor(&descriptor)
+            .inspect_err(|e| print_descriptor_error(&descriptor, e))?
+    } else {
+        TaskDescriptor {
+            task: prompt_for_task()?,
+            tags: Vec::new(),
+        }
+    };
+
+    if dry_run {
+        return print_task(&descriptor.task, descriptor.to_string().trim_end());
+    }
+
+    let TaskDescriptor { mut task, tags } = descriptor;
+    let mut limiter = RateLimiter::default();
+    if !tags.is_empty() {
+        let mut known_tags = fetch_tags().await?;
+        task.tags = resolve_tag_ids(&tags, &mut known_tags, &mut limiter).await?;
+    }
+    debug!("Creating task: \n{task}");
+
+    let created = limiter.send(|| post_created_task(task.clone())).await?;
+
+    print_task(&created, &format!("Created: \n{created}"))
+}
+
+fn is_rate_limited(e: &AppError) -> bool {
+    matches!(e, AppError::HTTPError(e) if e.status() == Some(StatusCode::TOO_MANY_REQUESTS))
+}
+
+/// Spaces out the requests of a bulk operation to stay within the Habitica
+/// API rate limit
+pub struct RateLimiter {
+    batch_started: Instant,
+    n_in_batch: usize,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            batch_started: Instant::now(),
+            n_in_batch: 0,
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Send a request once the rate limit allows it, retrying it once after a
+    /// minute if Habitica reports the limit as reached anyway
+    pub async fn send<T, F, Fut>(&mut self, request: F) -> Result<T, AppError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        if self.n_in_batch == RATE_LIMIT_BATCH_SIZE {
+            info!("Sent a batch of {RATE_LIMIT_BATCH_SIZE} requests, waiting for rate limit");
+            sleep(RATE_LIMIT_BATCH_WINDOW.saturating_sub(self.batch_started.elapsed())).await;
+            self.n_in_batch = 0;
+        }
+        if self.n_in_batch == 0 {
+            self.batch_started = Instant::now();
+        }
+        self.n_in_batch += 1;
+
+        let res = request().await;
+        if res.as_ref().is_err_and(is_rate_limited) {
+            info!("Rate limit reached, retrying in a minute");
+            sleep(std::time::Duration::from_secs(60)).await;
+            return request().await;
+        }
+        res
+    }
+}
+
+/// Create several tasks while staying within the Habitica API rate limit.
+/// Returns the outcome of each creation in the order the tasks were given
+pub async fn post_created_tasks(
+    tasks: Vec<Task>,
+    limiter: &mut RateLimiter,
+) -> Vec<Result<Task, AppError>> {
+    let mut results = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        results.push(limiter.send(|| post_created_task(task.clone())).await);
+    }
+
+    results
+}
+
+/// Map tag names to tag IDs, creating the tags which do not exist yet. Newly
+/// created tags are added to `known`
+pub async fn resolve_tag_ids(
+    names: &[String],
+    known: &mut Vec<Tag>,
+    limiter: &mut RateLimiter,
+) -> Result<Vec<Uuid>, AppError> {
+    let mut ids = Vec::with_capacity(names.len());
+
+    for name in names {
+        let existing = known.iter().find(|t| t.name.eq_ignore_ascii_case(name));
+        let id = match existing {
+            Some(tag) => tag.id,
+            None => {
+                let tag = limiter.send(|| post_created_tag(name)).await?;
+                known.push(tag.clone());
+                tag.id
+            }
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    Ok(ids)
+}
+
+pub fn register_tags(tags: Vec<Tag>) {
+    if let Ok(mut known) = KNOWN_TAGS.write() {
+        *known = tags;
+    }
+}
+
+/// Look up the names of the given tag IDs among the registered tags. Unknown
+/// tags are left out
+pub fn get_tag_names(ids: &[Uuid]) -> Vec<String> {
+    let Ok(known) = KNOWN_TAGS.read() else {
+        return Vec::new();
+    };
+    ids.iter()
+        .filter_map(|id| known.iter().find(|t| t.id == *id))
+        .map(|t| t.name.clone())
+        .collect()
+}
+
 pub async fn get_task_list() -> Result<Vec<Task>, AppError> {
+    // Priority rules matching on tags need to know the tag names
+    if get_config().priority.uses_tags() {
+        register_tags(fetch_tags().await?);
+    }
+
     let raw_tasks = fetch_tasks("todos").await?;
     let tasks = serde_json::from_str::<ArrayRes<Task>>(raw_tasks.as_str())?.data;
     Ok(tasks)
@@ -226,20 +557,19 @@ pub async fn list_tasks(save_json: bool) -> Result<(), AppError> {
     let raw_tasks = fetch_tasks("todos").await?;
     let tasks = serde_json::from_str::<ArrayRes<Task>>(raw_tasks.as_str())?.data;
 
-    for task in tasks {
-        println!("{task}");
-    }
+    print_tasks(&tasks)?;
 
     if save_json {
         let mut file = File::create(get_json_path()?)?;
         file.write_all(raw_tasks.as_bytes())?;
-        println!("\nSaved list to ~/.config/habitica_tasks.json");
+        print_status("\nSaved list to ~/.config/habitica_tasks.json");
     }
 
     Ok(())
 }
 
-pub async fn get_completed_tasks() -> Result<(), AppError> {
+/// Archive completed TODOs from Habitica into the completed_task table
+pub async fn sync_completed_tasks() -> Result<(), AppError> {
     let raw_tasks = fetch_tasks("completedTodos").await?;
     let tasks = serde_json::from_str::<ArrayRes<Task>>(raw_tasks.as_str())?.data;
 
@@ -273,33 +603,11 @@ pub async fn get_completed_tasks() -> Result<(), AppError> {
         .await?;
     }
 
-    for task in query_completed_tasks().await? {
-        println!("{task}");
-    }
-
     Ok(())
 }
 
-pub async fn priority_reorder_tasks() -> Result<(), AppError> {
-    let tasks = get_task_list().await?;
-    let mut prev_high_priority = 0;
-    let mut prev_mid_priority = 0;
+pub async fn get_completed_tasks(filter: HistoryFilter) -> Result<(), AppError> {
+    sync_completed_tasks().await?;
 
-    for task in tasks {
-        let prio = task.get_priority();
-        match prio {
-            Priority::LOW => {}
-            Priority::MID => {
-                reorder_task(task.id, prev_mid_priority).await?;
-                prev_mid_priority += 1;
-            }
-            Priority::HIGH => {
-                reorder_task(task.id, prev_high_priority).await?;
-                prev_high_priority += 1;
-                prev_mid_priority += 1;
-            }
-        }
-    }
-
-    Ok(())
+    print_tasks(&query_completed_tasks(&filter).await?)
 }
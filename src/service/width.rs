@@ -0,0 +1,23 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Width of a grapheme in terminal columns. Emoji presentation and joined
+/// emoji sequences, e.g. ❤️, are shown two columns wide regardless of the
+/// characters they consist of
+pub fn grapheme_width(grapheme: &str) -> usize {
+    if grapheme.contains(['\u{fe0f}', '\u{200d}']) {
+        2
+    } else {
+        grapheme.width()
+    }
+}
+
+/// Width of a string in terminal columns
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Pad a string with spaces to the given width
+pub fn pad_to_width(s: &str, width: usize) -> String {
+    format!("{s}{}", " ".repeat(width.saturating_sub(display_width(s))))
+}
@@ -1,4 +1,163 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use time::{format_description::well_known::Iso8601, Date};
+
+use crate::service::{
+    date::{local_today, parse_natural_date},
+    types::Difficulty,
+};
+
+fn parse_date(s: &str) -> Result<Date, String> {
+    parse_natural_date(s, local_today())
+        .map_or_else(|| Date::parse(s, &Iso8601::DATE), Ok)
+        .map_err(|_| format!("expected a date such as YYYY-MM-DD or 'yesterday', got '{s}'"))
+}
+
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected a variable as name=value, got '{s}'")),
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum HistorySort {
+    /// Completion time, newest first
+    #[default]
+    Completed,
+    /// Due date, earliest first
+    Due,
+    /// Difficulty, hardest first
+    Difficulty,
+    /// Task name, alphabetically
+    Name,
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum ExportFormat {
+    /// Comma separated values with one task per row
+    #[default]
+    Csv,
+    /// JSON array of normalised task objects
+    Json,
+    /// Markdown checklist
+    Markdown,
+    /// todo.txt with one task per line
+    Todotxt,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ImportFormat {
+    /// todo.txt with one task per line
+    Todotxt,
+    /// Markdown checklist, nested items become subtasks
+    Markdown,
+    /// JSON produced by `task export`
+    Taskwarrior,
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum ExportSource {
+    /// Active TODOs
+    #[default]
+    Todos,
+    /// Archived completed TODOs
+    History,
+    /// Both active and completed TODOs
+    All,
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Human,
+    /// JSON array of tasks, or a single JSON object for commands acting on
+    /// one task
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// Aligned columns with a header
+    Table,
+    /// Tab separated fields, one task per line
+    Plain,
+    /// Each task formatted with --format-template
+    Template,
+}
+
+#[derive(Args, Default)]
+pub struct HistoryFilter {
+    /// Only list tasks completed on or after this date (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_date)]
+    pub since: Option<Date>,
+
+    /// Only list tasks completed on or before this date (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_date)]
+    pub until: Option<Date>,
+
+    /// Only list tasks of the given difficulty. Can be given multiple times
+    #[arg(long, value_enum)]
+    pub difficulty: Vec<Difficulty>,
+
+    /// Only list tasks whose name, notes or checklist contain the given text
+    #[arg(long)]
+    pub search: Option<String>,
+
+    /// Maximum number of tasks to list
+    #[arg(long)]
+    pub limit: Option<u32>,
+
+    /// Order in which the tasks are listed
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: HistorySort,
+
+    /// Reverse the sort order
+    #[arg(long, default_value_t = false)]
+    pub reverse: bool,
+}
+
+#[derive(Subcommand)]
+pub enum RecurOperation {
+    /// Create TODOs from a template on a schedule
+    Add {
+        /// Name of the template in the templates section of the config
+        template: String,
+
+        /// When to create the TODO, e.g. "every monday", "every mon, thu",
+        /// "first of month", "last of month" or "every 10 days"
+        rule: String,
+
+        /// Value of a variable of the template as name=value. Can be given
+        /// multiple times. {date} is the date of the occurrence
+        #[arg(long = "var", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// First day the rule applies to, defaults to today. Intervals such
+        /// as "every 10 days" are counted from it
+        #[arg(long, value_parser = parse_date)]
+        start: Option<Date>,
+    },
+
+    /// List the recurrences and their next occurrence
+    List,
+
+    /// Delete a recurrence
+    Rm {
+        /// ID of the recurrence as shown by `recur list`
+        id: i32,
+    },
+
+    /// Create the TODOs of the recurrences which are due. Meant to be run
+    /// periodically, e.g. daily from cron
+    Run {
+        /// Only print the TODOs that would be created
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
 
 #[derive(Subcommand)]
 pub enum Operation {
@@ -10,17 +169,197 @@ pub enum Operation {
     },
 
     /// List completed TODOs
-    History,
+    History(HistoryFilter),
 
     /// Create a new TODO item
     Task {
         /// Optionally define TODO item with a descriptor. Format:
         /// <name>,<difficulty>,<notes>,<due>,<checklist1>;<checklist2>;...
+        /// Fields can also be named in any order, e.g.
+        /// "Buy milk,due:2024-01-01,tag:errands" or "tag:errands,Buy milk",
+        /// where a single field without a name is the name of the TODO. Quote
+        /// values or escape characters with \ to use commas in them. Use - to
+        /// read descriptors from stdin, one per line
         descriptor: Option<String>,
+
+        /// Create a TODO item for each line of a file of descriptors. Empty
+        /// lines and lines starting with # are skipped
+        #[arg(short, long, conflicts_with = "descriptor")]
+        file: Option<PathBuf>,
+
+        /// Create the TODO from a template in the templates section of the
+        /// config
+        #[arg(long, conflicts_with_all = ["descriptor", "file"])]
+        template: Option<String>,
+
+        /// Value of a variable of the template as name=value. Can be given
+        /// multiple times
+        #[arg(long = "var", value_parser = parse_var, requires = "template")]
+        vars: Vec<(String, String)>,
+
+        /// Only validate the descriptors and print the TODOs they define
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+
+    /// Complete a TODO
+    Done {
+        /// Start of the ID or part of the name of the TODO
+        query: String,
+    },
+
+    /// Delete a TODO
+    Rm {
+        /// Start of the ID or part of the name of the TODO
+        query: String,
+
+        /// Delete without asking for confirmation
+        #[arg(short, long, default_value_t = false)]
+        yes: bool,
+    },
+
+    /// Edit a TODO
+    Edit {
+        /// Start of the ID or part of the name of the TODO
+        query: String,
+
+        /// New name of the TODO
+        #[arg(long)]
+        name: Option<String>,
+
+        /// New notes of the TODO, an empty string removes the notes
+        #[arg(long)]
+        notes: Option<String>,
+
+        /// New due date, e.g. 2024-12-24, tomorrow or +3d
+        #[arg(long)]
+        due: Option<String>,
+
+        /// Remove the due date
+        #[arg(long, default_value_t = false, conflicts_with = "due")]
+        no_due: bool,
+
+        /// New difficulty of the TODO
+        #[arg(short, long, value_enum)]
+        difficulty: Option<Difficulty>,
+
+        /// Edit the TODO as a Markdown document in $VISUAL or $EDITOR
+        #[arg(
+            short,
+            long,
+            default_value_t = false,
+            conflicts_with_all = ["name", "notes", "due", "no_due", "difficulty"]
+        )]
+        external: bool,
+    },
+
+    /// Move a TODO to another position in the list
+    Move {
+        /// Start of the ID or part of the name of the TODO
+        query: String,
+
+        /// Position to move the TODO to, starting from 1
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        to: u64,
+    },
+
+    /// Complete a subtask of a TODO
+    Check {
+        /// Start of the ID or part of the name of the TODO
+        query: String,
+
+        /// Position of the subtask starting from 1, or part of its text
+        subtask: String,
+
+        /// Mark the subtask as not completed instead
+        #[arg(long, default_value_t = false)]
+        uncheck: bool,
+    },
+
+    /// Print a one line summary of TODOs, dailies and health, e.g. for a
+    /// status bar. JSON output is in the format of waybar custom modules
+    Status {
+        /// Format of the line, e.g. "{todos} TODOs, {overdue} overdue". Fields
+        /// are todos, overdue, high, dailies, hp and max_hp
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// Fetch the summary from Habitica even if the cached one is fresh
+        #[arg(long, default_value_t = false)]
+        refresh: bool,
     },
 
     /// Reorder tasks by descending priority
-    Reorder,
+    Reorder {
+        /// Only print the current and the planned order of the TODOs
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+
+    /// Show the priority of TODOs and the rule which assigned it
+    Priority {
+        /// Only show TODOs matching the query by the start of their ID or by
+        /// their name
+        query: Option<String>,
+    },
+
+    /// Export TODOs to a file
+    Export {
+        /// Format of the exported file
+        #[arg(short, long, value_enum, default_value_t)]
+        format: ExportFormat,
+
+        /// Which TODOs to export
+        #[arg(short, long, value_enum, default_value_t)]
+        tasks: ExportSource,
+
+        /// File to write the export to. Writes to stdout if omitted or "-"
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Generate an iCalendar file of TODO due dates
+    Ical {
+        /// File to write the calendar to. Defaults to
+        /// ~/.config/habitui/habitica_todos.ics
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Keep running and refresh the calendar every given number of minutes
+        #[arg(short, long)]
+        watch: Option<u64>,
+    },
+
+    /// Send desktop notifications when TODOs are about to be due and for
+    /// Habitica reminders. Lead times and quiet hours are set in the daemon
+    /// section of the config
+    Daemon {
+        /// Check once and exit instead of checking periodically
+        #[arg(long, default_value_t = false)]
+        once: bool,
+    },
+
+    /// Manage TODOs which are created again on a schedule
+    #[command(subcommand)]
+    Recur(RecurOperation),
+
+    /// Import TODOs from a file
+    Import {
+        /// File to import. Reads from stdin if "-"
+        file: PathBuf,
+
+        /// Format of the imported file. Guessed from the file extension if omitted
+        #[arg(short, long, value_enum)]
+        format: Option<ImportFormat>,
+
+        /// Only preview the TODOs that would be created
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+
+        /// Create the TODOs without asking for confirmation
+        #[arg(short, long, default_value_t = false)]
+        yes: bool,
+    },
 }
 
 #[derive(Parser)]
@@ -37,4 +376,14 @@ pub struct Cli {
     /// Turn debugging information on
     #[arg(short, long, default_value_t = false)]
     pub debug: bool,
+
+    /// Format of the printed tasks
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub output_format: OutputFormat,
+
+    /// Format each task with a template such as "{text} [{difficulty}] due {due}".
+    /// Fields: id, short_id, text, difficulty, priority, notes, due,
+    /// completed, completed_at, checklist, checklist_done, checklist_total
+    #[arg(long, global = true)]
+    pub format_template: Option<String>,
 }
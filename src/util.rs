@@ -9,13 +9,12 @@ const HABITUI_CONFIG_DIR: &str = ".config/habitui";
 pub fn build_config_path() -> Result<String, AppError> {
     let sudo_user_var = env::var("SUDO_USER");
     let home_var = env::var("HOME");
-    let dir: String;
 
-    match (sudo_user_var, home_var) {
-        (Ok(user), _) => dir = format!("/home/{user}/{HABITUI_CONFIG_DIR}"),
-        (_, Ok(home)) => dir = format!("{home}/{HABITUI_CONFIG_DIR}"),
+    let dir = match (sudo_user_var, home_var) {
+        (Ok(user), _) => format!("/home/{user}/{HABITUI_CONFIG_DIR}"),
+        (_, Ok(home)) => format!("{home}/{HABITUI_CONFIG_DIR}"),
         (Err(_), Err(e)) => return Err(e.into()),
-    }
+    };
 
     Ok(dir)
 }
@@ -25,7 +24,7 @@ pub fn load_env() -> Result<(), AppError> {
     let dir = build_config_path()?;
 
     // Go to config dir and pull .env contents
-    if let Err(_) = env::set_current_dir(dir) {
+    if env::set_current_dir(dir).is_err() {
         return Err(AppError::ServiceError(
             "$HOME/.config/habitui not found".to_string(),
         ));